@@ -0,0 +1,11 @@
+drop table oracle_attestations;
+
+drop index sigs_bet_id_outcome_idx;
+ALTER TABLE sigs
+    DROP COLUMN oracle_combination;
+create unique index sigs_bet_id_outcome_idx on sigs (bet_id, outcome, is_party_a);
+
+drop table bet_oracles;
+
+ALTER TABLE bets
+    DROP COLUMN threshold;
@@ -0,0 +1,33 @@
+ALTER TABLE bets
+    ADD COLUMN threshold INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE bet_oracles
+(
+    id                  SERIAL PRIMARY KEY,
+    bet_id              integer NOT NULL,
+    oracle_index        integer NOT NULL,
+    oracle_announcement bytea   NOT NULL,
+    oracle_event_id     bytea   NOT NULL,
+    FOREIGN KEY (bet_id) REFERENCES bets (id)
+);
+
+create unique index bet_oracles_bet_id_oracle_index_idx on bet_oracles (bet_id, oracle_index);
+create index bet_oracles_oracle_event_id_idx on bet_oracles (oracle_event_id);
+
+INSERT INTO bet_oracles (bet_id, oracle_index, oracle_announcement, oracle_event_id)
+SELECT id, 0, oracle_announcement, oracle_event_id
+FROM bets;
+
+ALTER TABLE sigs
+    ADD COLUMN oracle_combination INTEGER NOT NULL DEFAULT 0;
+
+drop index sigs_bet_id_outcome_idx;
+create unique index sigs_bet_id_outcome_idx on sigs (bet_id, outcome, is_party_a, oracle_combination);
+
+CREATE TABLE oracle_attestations
+(
+    id              SERIAL PRIMARY KEY,
+    oracle_event_id bytea     NOT NULL UNIQUE,
+    attestation     bytea     NOT NULL,
+    created_at      TIMESTAMP NOT NULL DEFAULT NOW()
+);
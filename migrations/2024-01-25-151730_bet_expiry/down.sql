@@ -0,0 +1,4 @@
+ALTER TABLE bets
+    DROP COLUMN expired_at;
+ALTER TABLE bets
+    DROP COLUMN expires_at;
@@ -0,0 +1,10 @@
+ALTER TABLE bets
+    ADD COLUMN expires_at TIMESTAMP;
+ALTER TABLE bets
+    ADD COLUMN expired_at TIMESTAMP;
+
+UPDATE bets
+SET expires_at = created_at + INTERVAL '7 days';
+
+ALTER TABLE bets
+    ALTER COLUMN expires_at SET NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE bets
+    DROP COLUMN voided_at;
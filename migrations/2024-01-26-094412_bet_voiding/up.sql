@@ -0,0 +1,2 @@
+ALTER TABLE bets
+    ADD COLUMN voided_at TIMESTAMP;
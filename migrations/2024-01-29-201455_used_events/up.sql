@@ -0,0 +1,8 @@
+CREATE TABLE used_events
+(
+    event_id   bytea PRIMARY KEY,
+    pubkey     bytea     NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+create index used_events_created_at_idx on used_events (created_at);
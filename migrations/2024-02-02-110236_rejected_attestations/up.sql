@@ -0,0 +1,12 @@
+CREATE TABLE rejected_attestations
+(
+    id              SERIAL PRIMARY KEY,
+    event_id        bytea     NOT NULL,
+    oracle_event_id bytea     NOT NULL,
+    pubkey          bytea     NOT NULL,
+    content         TEXT      NOT NULL,
+    reason          TEXT      NOT NULL,
+    created_at      TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+create index rejected_attestations_oracle_event_id_idx on rejected_attestations (oracle_event_id);
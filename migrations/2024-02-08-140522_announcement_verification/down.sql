@@ -0,0 +1,2 @@
+ALTER TABLE bet_oracles
+    DROP COLUMN announcement_verified;
@@ -0,0 +1,2 @@
+ALTER TABLE bet_oracles
+    ADD COLUMN announcement_verified BOOLEAN NOT NULL DEFAULT FALSE;
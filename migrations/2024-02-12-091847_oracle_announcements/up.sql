@@ -0,0 +1,7 @@
+CREATE TABLE oracle_announcements
+(
+    id              SERIAL PRIMARY KEY,
+    oracle_event_id bytea     NOT NULL UNIQUE,
+    announcement    bytea     NOT NULL,
+    created_at      TIMESTAMP NOT NULL DEFAULT NOW()
+);
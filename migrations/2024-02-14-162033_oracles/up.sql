@@ -0,0 +1,11 @@
+CREATE TABLE oracles
+(
+    id           SERIAL PRIMARY KEY,
+    pubkey       bytea     NOT NULL UNIQUE,
+    name         TEXT      NOT NULL,
+    description  TEXT,
+    relays       TEXT[]    NOT NULL DEFAULT '{}',
+    trust_status TEXT      NOT NULL DEFAULT 'trusted',
+    created_at   TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,6 @@
+CREATE TABLE user_settings
+(
+    pubkey     bytea PRIMARY KEY,
+    dm_opt_out BOOLEAN   NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,2 @@
+ALTER TABLE user_settings
+    ADD COLUMN private BOOLEAN NOT NULL DEFAULT FALSE;
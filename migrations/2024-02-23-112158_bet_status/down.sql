@@ -0,0 +1,15 @@
+drop index bets_status_idx;
+
+ALTER TABLE bets
+    ADD COLUMN needs_reply BOOLEAN NOT NULL DEFAULT TRUE;
+
+UPDATE bets
+SET needs_reply = (status = 'proposed');
+
+UPDATE bets
+SET win_outcome_event_id  = '\x0000000000000000000000000000000000000000000000000000000000000000'::bytea,
+    lose_outcome_event_id = '\x0000000000000000000000000000000000000000000000000000000000000000'::bytea
+WHERE status = 'no_contest';
+
+ALTER TABLE bets
+    DROP COLUMN status;
@@ -0,0 +1,32 @@
+ALTER TABLE bets
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'proposed'
+        CHECK (status IN ('proposed', 'active', 'attested', 'settling', 'settled', 'no_contest',
+                          'rejected', 'expired', 'voided', 'failed'));
+
+UPDATE bets
+SET status = CASE
+    WHEN expired_at IS NOT NULL THEN 'expired'
+    WHEN voided_at IS NOT NULL THEN 'voided'
+    WHEN settlement_error IS NOT NULL THEN 'failed'
+    WHEN needs_reply THEN 'proposed'
+    WHEN win_outcome_event_id = '\x0000000000000000000000000000000000000000000000000000000000000000'::bytea
+        THEN 'no_contest'
+    WHEN win_outcome_event_id IS NOT NULL OR lose_outcome_event_id IS NOT NULL THEN 'settled'
+    WHEN EXISTS (SELECT 1
+                 FROM bet_oracles
+                          JOIN oracle_attestations
+                               ON oracle_attestations.oracle_event_id = bet_oracles.oracle_event_id
+                 WHERE bet_oracles.bet_id = bets.id) THEN 'attested'
+    ELSE 'active'
+    END;
+
+-- no contest bets used to be marked with all zero outcome event ids
+UPDATE bets
+SET win_outcome_event_id  = NULL,
+    lose_outcome_event_id = NULL
+WHERE status = 'no_contest';
+
+ALTER TABLE bets
+    DROP COLUMN needs_reply;
+
+create index bets_status_idx on bets (status);
@@ -0,0 +1,23 @@
+DROP TABLE bet_history;
+
+DELETE
+FROM sigs
+WHERE bet_id IN (SELECT id FROM bets WHERE status IN ('rejected', 'cancelled'));
+DELETE
+FROM bet_oracles
+WHERE bet_id IN (SELECT id FROM bets WHERE status IN ('rejected', 'cancelled'));
+DELETE
+FROM bets
+WHERE status IN ('rejected', 'cancelled');
+
+ALTER TABLE bets
+    DROP CONSTRAINT bets_status_check;
+ALTER TABLE bets
+    ADD CONSTRAINT bets_status_check
+        CHECK (status IN ('proposed', 'active', 'attested', 'settling', 'settled', 'no_contest',
+                          'rejected', 'expired', 'voided', 'failed'));
+
+ALTER TABLE bets
+    DROP COLUMN rejected_by,
+    DROP COLUMN rejected_at,
+    DROP COLUMN reject_event;
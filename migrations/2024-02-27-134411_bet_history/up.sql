@@ -0,0 +1,30 @@
+ALTER TABLE bets
+    ADD COLUMN rejected_by  bytea,
+    ADD COLUMN rejected_at  TIMESTAMP,
+    ADD COLUMN reject_event jsonb;
+
+ALTER TABLE bets
+    DROP CONSTRAINT bets_status_check;
+ALTER TABLE bets
+    ADD CONSTRAINT bets_status_check
+        CHECK (status IN ('proposed', 'active', 'attested', 'settling', 'settled', 'no_contest',
+                          'rejected', 'cancelled', 'expired', 'voided', 'failed'));
+
+CREATE TABLE bet_history
+(
+    id          SERIAL PRIMARY KEY,
+    bet_id      integer   NOT NULL,
+    from_status TEXT,
+    to_status   TEXT      NOT NULL,
+    actor       bytea,
+    event       jsonb,
+    created_at  TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (bet_id) REFERENCES bets (id)
+);
+
+create index bet_history_bet_id_idx on bet_history (bet_id);
+
+-- start the history of existing bets from where they are now
+INSERT INTO bet_history (bet_id, from_status, to_status, created_at)
+SELECT id, NULL, status, created_at
+FROM bets;
@@ -0,0 +1,20 @@
+DELETE
+FROM bet_history
+WHERE bet_id IN (SELECT id FROM bets WHERE user_b IS NULL);
+DELETE
+FROM sigs
+WHERE bet_id IN (SELECT id FROM bets WHERE user_b IS NULL);
+DELETE
+FROM bet_oracles
+WHERE bet_id IN (SELECT id FROM bets WHERE user_b IS NULL);
+DELETE
+FROM bets
+WHERE user_b IS NULL;
+
+ALTER TABLE bets
+    DROP CONSTRAINT bets_counterparty_check,
+    DROP COLUMN counterparty_win_template,
+    DROP COLUMN counterparty_lose_template,
+    ALTER COLUMN user_b SET NOT NULL,
+    ALTER COLUMN win_b SET NOT NULL,
+    ALTER COLUMN lose_b SET NOT NULL;
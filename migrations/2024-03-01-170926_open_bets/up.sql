@@ -0,0 +1,14 @@
+ALTER TABLE bets
+    ALTER COLUMN user_b DROP NOT NULL,
+    ALTER COLUMN win_b DROP NOT NULL,
+    ALTER COLUMN lose_b DROP NOT NULL,
+    ADD COLUMN counterparty_win_template  jsonb,
+    ADD COLUMN counterparty_lose_template jsonb;
+
+-- open bets get their counterparty's notes when they're accepted
+ALTER TABLE bets
+    ADD CONSTRAINT bets_counterparty_check
+        CHECK ((user_b IS NOT NULL AND win_b IS NOT NULL AND lose_b IS NOT NULL)
+            OR (user_b IS NULL AND win_b IS NULL AND lose_b IS NULL
+                AND counterparty_win_template IS NOT NULL
+                AND counterparty_lose_template IS NOT NULL));
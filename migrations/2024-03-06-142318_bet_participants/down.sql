@@ -0,0 +1,82 @@
+-- bets with more than two participants can't be represented anymore
+DELETE
+FROM bet_history
+WHERE bet_id IN (SELECT id FROM bets WHERE participant_count > 2);
+DELETE
+FROM sigs
+WHERE bet_id IN (SELECT id FROM bets WHERE participant_count > 2);
+DELETE
+FROM bet_oracles
+WHERE bet_id IN (SELECT id FROM bets WHERE participant_count > 2);
+DELETE
+FROM bet_participants
+WHERE bet_id IN (SELECT id FROM bets WHERE participant_count > 2);
+DELETE
+FROM bets
+WHERE participant_count > 2;
+
+ALTER TABLE bets
+    ADD COLUMN user_a                bytea,
+    ADD COLUMN win_a                 jsonb,
+    ADD COLUMN lose_a                jsonb,
+    ADD COLUMN user_b                bytea,
+    ADD COLUMN win_b                 jsonb,
+    ADD COLUMN lose_b                jsonb,
+    ADD COLUMN win_outcome_event_id  bytea,
+    ADD COLUMN lose_outcome_event_id bytea;
+
+UPDATE bets
+SET user_a = p.pubkey,
+    win_a  = p.win_event,
+    lose_a = p.lose_event
+FROM bet_participants p
+WHERE p.bet_id = bets.id
+  AND p.participant_index = 0;
+
+UPDATE bets
+SET user_b = p.pubkey,
+    win_b  = p.win_event,
+    lose_b = p.lose_event
+FROM bet_participants p
+WHERE p.bet_id = bets.id
+  AND p.participant_index = 1;
+
+UPDATE bets
+SET win_outcome_event_id = p.outcome_event_id
+FROM bet_participants p
+WHERE p.bet_id = bets.id
+  AND encode(p.outcome_event_id, 'hex') = p.win_event ->> 'id';
+
+UPDATE bets
+SET lose_outcome_event_id = p.outcome_event_id
+FROM bet_participants p
+WHERE p.bet_id = bets.id
+  AND encode(p.outcome_event_id, 'hex') = p.lose_event ->> 'id';
+
+ALTER TABLE bets
+    ALTER COLUMN user_a SET NOT NULL,
+    ALTER COLUMN win_a SET NOT NULL,
+    ALTER COLUMN lose_a SET NOT NULL,
+    DROP COLUMN participant_count,
+    ADD CONSTRAINT bets_counterparty_check
+        CHECK ((user_b IS NOT NULL AND win_b IS NOT NULL AND lose_b IS NOT NULL)
+            OR (user_b IS NULL AND win_b IS NULL AND lose_b IS NULL
+                AND counterparty_win_template IS NOT NULL
+                AND counterparty_lose_template IS NOT NULL));
+
+create index bets_user_a_idx on bets (user_a);
+create index bets_user_b_idx on bets (user_b);
+
+ALTER TABLE sigs
+    ADD COLUMN is_party_a boolean NOT NULL DEFAULT true;
+UPDATE sigs
+SET is_party_a = participant_index = 0;
+ALTER TABLE sigs
+    ALTER COLUMN is_party_a DROP DEFAULT;
+
+drop index sigs_bet_id_outcome_idx;
+ALTER TABLE sigs
+    DROP COLUMN participant_index;
+create unique index sigs_bet_id_outcome_idx on sigs (bet_id, outcome, is_party_a, oracle_combination);
+
+DROP TABLE bet_participants;
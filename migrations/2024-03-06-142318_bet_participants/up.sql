@@ -0,0 +1,82 @@
+CREATE TABLE bet_participants
+(
+    id                SERIAL PRIMARY KEY,
+    bet_id            integer   NOT NULL,
+    participant_index integer   NOT NULL,
+    pubkey            bytea     NOT NULL,
+    win_event         jsonb     NOT NULL,
+    lose_event        jsonb     NOT NULL,
+    accepted_at       TIMESTAMP,
+    outcome_event_id  bytea,
+    created_at        TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (bet_id) REFERENCES bets (id)
+);
+
+create unique index bet_participants_bet_id_participant_index_idx on bet_participants (bet_id, participant_index);
+create unique index bet_participants_bet_id_pubkey_idx on bet_participants (bet_id, pubkey);
+create index bet_participants_pubkey_idx on bet_participants (pubkey);
+
+-- the proposer is always the first participant
+INSERT INTO bet_participants (bet_id, participant_index, pubkey, win_event, lose_event, accepted_at,
+                              outcome_event_id, created_at)
+SELECT id,
+       0,
+       user_a,
+       win_a,
+       lose_a,
+       created_at,
+       CASE
+           WHEN encode(win_outcome_event_id, 'hex') = win_a ->> 'id' THEN win_outcome_event_id
+           WHEN encode(lose_outcome_event_id, 'hex') = lose_a ->> 'id' THEN lose_outcome_event_id
+           END,
+       created_at
+FROM bets;
+
+INSERT INTO bet_participants (bet_id, participant_index, pubkey, win_event, lose_event, accepted_at,
+                              outcome_event_id, created_at)
+SELECT id,
+       1,
+       user_b,
+       win_b,
+       lose_b,
+       CASE
+           WHEN status IN ('active', 'attested', 'settling', 'settled', 'no_contest', 'voided', 'failed')
+               THEN created_at
+           END,
+       CASE
+           WHEN encode(win_outcome_event_id, 'hex') = win_b ->> 'id' THEN win_outcome_event_id
+           WHEN encode(lose_outcome_event_id, 'hex') = lose_b ->> 'id' THEN lose_outcome_event_id
+           END,
+       created_at
+FROM bets
+WHERE user_b IS NOT NULL;
+
+ALTER TABLE sigs
+    ADD COLUMN participant_index integer NOT NULL DEFAULT 0;
+UPDATE sigs
+SET participant_index = 1
+WHERE NOT is_party_a;
+ALTER TABLE sigs
+    ALTER COLUMN participant_index DROP DEFAULT;
+
+drop index sigs_bet_id_outcome_idx;
+ALTER TABLE sigs
+    DROP COLUMN is_party_a;
+create unique index sigs_bet_id_outcome_idx on sigs (bet_id, outcome, participant_index, oracle_combination);
+
+-- existing bets are all between two people
+ALTER TABLE bets
+    ADD COLUMN participant_count integer NOT NULL DEFAULT 2;
+ALTER TABLE bets
+    ALTER COLUMN participant_count DROP DEFAULT;
+
+ALTER TABLE bets
+    DROP CONSTRAINT bets_counterparty_check,
+    DROP COLUMN user_a,
+    DROP COLUMN win_a,
+    DROP COLUMN lose_a,
+    DROP COLUMN user_b,
+    DROP COLUMN win_b,
+    DROP COLUMN lose_b,
+    DROP COLUMN win_outcome_event_id,
+    DROP COLUMN lose_outcome_event_id;
@@ -0,0 +1,35 @@
+-- bets with draw outcomes can't be represented anymore, their draw sigs
+-- would be taken for lose sigs
+DELETE
+FROM bet_history
+WHERE bet_id IN (SELECT id FROM bets WHERE draw_outcomes <> '{}');
+DELETE
+FROM sigs
+WHERE bet_id IN (SELECT id FROM bets WHERE draw_outcomes <> '{}');
+DELETE
+FROM bet_oracles
+WHERE bet_id IN (SELECT id FROM bets WHERE draw_outcomes <> '{}');
+DELETE
+FROM bet_participants
+WHERE bet_id IN (SELECT id FROM bets WHERE draw_outcomes <> '{}');
+DELETE
+FROM bets
+WHERE draw_outcomes <> '{}';
+
+ALTER TABLE bets
+    DROP CONSTRAINT bets_status_check;
+ALTER TABLE bets
+    ADD CONSTRAINT bets_status_check
+        CHECK (status IN ('proposed', 'active', 'attested', 'settling', 'settled', 'no_contest',
+                          'rejected', 'cancelled', 'expired', 'voided', 'failed'));
+
+ALTER TABLE bets
+    DROP COLUMN counterparty_draw_template,
+    DROP COLUMN draw_outcomes,
+    DROP COLUMN no_contest_outcomes;
+
+ALTER TABLE bet_participants
+    DROP COLUMN draw_event;
+
+ALTER TABLE sigs
+    DROP COLUMN is_draw;
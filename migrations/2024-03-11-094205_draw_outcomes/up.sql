@@ -0,0 +1,20 @@
+-- sigs on a draw note are neither a win nor a loss
+ALTER TABLE sigs
+    ADD COLUMN is_draw BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE bet_participants
+    ADD COLUMN draw_event jsonb;
+
+-- outcomes declared as a draw or as no contest when the bet was proposed,
+-- nothing is signed for no contest outcomes
+ALTER TABLE bets
+    ADD COLUMN counterparty_draw_template jsonb,
+    ADD COLUMN draw_outcomes              TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN no_contest_outcomes        TEXT[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE bets
+    DROP CONSTRAINT bets_status_check;
+ALTER TABLE bets
+    ADD CONSTRAINT bets_status_check
+        CHECK (status IN ('proposed', 'active', 'attested', 'settling', 'settled', 'drawn',
+                          'no_contest', 'rejected', 'cancelled', 'expired', 'voided', 'failed'));
@@ -0,0 +1,110 @@
+use crate::error::ApiError;
+use crate::models::oracle_announcement::CachedAnnouncement;
+use crate::utils::oracle_announcement_from_str;
+use crate::State;
+use dlc_messages::oracle_msgs::OracleAnnouncement;
+use lightning::util::ser::Writeable;
+use log::warn;
+use nostr::{Event, EventId, Filter, Kind};
+use nostr_database::NostrDatabase;
+use std::time::Duration;
+
+/// Kind of oracle announcement events
+pub const ANNOUNCEMENT_KIND: u64 = 88;
+
+/// Looks up the announcement event, first in the events DB and then on the
+/// relays.
+async fn fetch_announcement_event(
+    state: &State,
+    event_id: EventId,
+) -> anyhow::Result<Option<Event>> {
+    let filter = Filter::new()
+        .id(event_id)
+        .kind(Kind::Custom(ANNOUNCEMENT_KIND));
+
+    let stored = state
+        .nostr_client
+        .database()
+        .query(vec![filter.clone()], Default::default())
+        .await?;
+    if let Some(event) = stored.into_iter().next() {
+        return Ok(Some(event));
+    }
+
+    let timeout = Duration::from_secs(state.config.announcement_fetch_timeout_secs);
+    let events = state
+        .nostr_client
+        .get_events_of(vec![filter], Some(timeout))
+        .await?;
+    Ok(events.into_iter().find(|e| e.id == event_id))
+}
+
+/// Parses the announcement out of an announcement event, checking both the
+/// event and the oracle's signature on the announcement.
+fn announcement_from_event(state: &State, event: &Event) -> anyhow::Result<OracleAnnouncement> {
+    if event.verify().is_err() {
+        anyhow::bail!(ApiError::InvalidSignature(format!(
+            "Invalid announcement event {}",
+            event.id
+        )));
+    }
+
+    let announcement = oracle_announcement_from_str(&event.content).map_err(|_| {
+        ApiError::BadRequest(format!("Invalid oracle announcement in event {}", event.id))
+    })?;
+    announcement.validate(&state.secp).map_err(|_| {
+        ApiError::InvalidSignature(format!("Invalid oracle announcement in event {}", event.id))
+    })?;
+
+    Ok(announcement)
+}
+
+/// Resolves the oracle announcement for an announcement event id, caching it
+/// once it has been validated. Returns `None` if the event can't be found.
+pub async fn resolve_announcement(
+    state: &State,
+    event_id: EventId,
+) -> anyhow::Result<Option<OracleAnnouncement>> {
+    let mut conn = state.db_pool.get()?;
+    if let Some(cached) = CachedAnnouncement::get_by_oracle_event(&mut conn, &event_id)? {
+        return Ok(Some(cached.announcement()));
+    }
+    drop(conn);
+
+    let Some(event) = fetch_announcement_event(state, event_id).await? else {
+        return Ok(None);
+    };
+    let announcement = announcement_from_event(state, &event)?;
+
+    let mut conn = state.db_pool.get()?;
+    CachedAnnouncement::create(&mut conn, event_id, &announcement)?;
+
+    Ok(Some(announcement))
+}
+
+/// Checks the oracle event id points at a nostr event for this exact
+/// announcement, returning whether it could be verified. A mismatch is always
+/// an error, a missing event unless unverified announcements are allowed.
+pub async fn verify_announcement_event(
+    state: &State,
+    announcement: &OracleAnnouncement,
+    event_id: EventId,
+) -> anyhow::Result<bool> {
+    let Some(resolved) = resolve_announcement(state, event_id).await? else {
+        if !state.config.allow_unverified_announcements {
+            anyhow::bail!(ApiError::BadRequest(format!(
+                "Announcement event {event_id} not found"
+            )));
+        }
+        warn!("Announcement event {event_id} not found, accepting unverified");
+        return Ok(false);
+    };
+
+    if resolved.encode() != announcement.encode() {
+        anyhow::bail!(ApiError::BadRequest(format!(
+            "Announcement event {event_id} does not match the oracle announcement"
+        )));
+    }
+
+    Ok(true)
+}
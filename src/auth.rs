@@ -0,0 +1,329 @@
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::models::used_event::UsedEvent;
+use crate::State;
+use axum::http::header::{AUTHORIZATION, HOST};
+use axum::http::{HeaderMap, Method, Uri};
+use nostr::hashes::{sha256, Hash};
+use nostr::key::XOnlyPublicKey;
+use nostr::{Event, JsonUtil, Timestamp, Url};
+
+/// Kind of NIP-98 HTTP auth events
+const HTTP_AUTH_KIND: u64 = 27235;
+
+/// Returns the NIP-98 auth event from the request's `Authorization` header,
+/// if there is one.
+pub fn get_auth_event(headers: &HeaderMap) -> Result<Option<Event>, ApiError> {
+    let Some(header) = headers.get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+
+    let invalid = || ApiError::Unauthorized("invalid authorization header".to_string());
+    let encoded = header
+        .to_str()
+        .ok()
+        .and_then(|h| h.strip_prefix("Nostr "))
+        .ok_or_else(invalid)?;
+    let json = base64::decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(invalid)?;
+    let event = Event::from_json(json).map_err(|_| invalid())?;
+
+    Ok(Some(event))
+}
+
+/// Verifies a NIP-98 auth event was signed for this exact request, returning
+/// the pubkey that signed it.
+pub fn verify_auth_event(
+    config: &Config,
+    event: &Event,
+    headers: &HeaderMap,
+    method: &Method,
+    uri: &Uri,
+    body: Option<&[u8]>,
+) -> Result<XOnlyPublicKey, ApiError> {
+    let unauthorized = |msg: &str| ApiError::Unauthorized(msg.to_string());
+
+    if event.kind.as_u64() != HTTP_AUTH_KIND {
+        return Err(unauthorized("invalid auth event kind"));
+    }
+    if event.verify().is_err() {
+        return Err(unauthorized("invalid auth event signature"));
+    }
+
+    let now = Timestamp::now().as_u64();
+    let created_at = event.created_at.as_u64();
+    if now.abs_diff(created_at) > config.auth_window_secs {
+        return Err(unauthorized("auth event expired"));
+    }
+
+    let url = get_tag(event, "u").ok_or(unauthorized("auth event missing url"))?;
+    if !url_matches(config, &url, headers, uri) {
+        return Err(unauthorized("auth event url does not match"));
+    }
+
+    let auth_method = get_tag(event, "method").ok_or(unauthorized("auth event missing method"))?;
+    if !auth_method.eq_ignore_ascii_case(method.as_str()) {
+        return Err(unauthorized("auth event method does not match"));
+    }
+
+    if let Some(body) = body.filter(|b| !b.is_empty()) {
+        let payload = sha256::Hash::hash(body).to_string();
+        if get_tag(event, "payload").map(|p| p.to_lowercase()) != Some(payload) {
+            return Err(unauthorized("auth event payload does not match"));
+        }
+    }
+
+    Ok(event.pubkey)
+}
+
+/// Verifies the request's NIP-98 auth, if it has any.
+pub fn authenticate(
+    config: &Config,
+    headers: &HeaderMap,
+    method: &Method,
+    uri: &Uri,
+    body: Option<&[u8]>,
+) -> Result<Option<XOnlyPublicKey>, ApiError> {
+    match get_auth_event(headers)? {
+        Some(event) => verify_auth_event(config, &event, headers, method, uri, body).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Requires NIP-98 auth covering the request body, returning the pubkey that
+/// signed it. Each auth event can only be used once.
+pub fn require_auth(
+    state: &State,
+    headers: &HeaderMap,
+    method: &Method,
+    uri: &Uri,
+    body: &[u8],
+) -> anyhow::Result<XOnlyPublicKey> {
+    let event = get_auth_event(headers)?.ok_or(ApiError::Unauthorized(
+        "authentication required".to_string(),
+    ))?;
+    let pubkey = verify_auth_event(&state.config, &event, headers, method, uri, Some(body))?;
+
+    let mut conn = state.db_pool.get()?;
+    UsedEvent::mark_used(&mut conn, event.id, pubkey)?;
+
+    Ok(pubkey)
+}
+
+/// Requires NIP-98 auth from one of the configured admin pubkeys.
+pub fn require_admin(
+    state: &State,
+    headers: &HeaderMap,
+    method: &Method,
+    uri: &Uri,
+    body: &[u8],
+) -> anyhow::Result<XOnlyPublicKey> {
+    let pubkey = require_auth(state, headers, method, uri, body)?;
+    if !state.config.admin_pubkey.contains(&pubkey) {
+        anyhow::bail!(ApiError::Forbidden("admin only".to_string()));
+    }
+    Ok(pubkey)
+}
+
+fn get_tag(event: &Event, name: &str) -> Option<String> {
+    event.tags.iter().find_map(|t| {
+        let tag = t.as_vec();
+        if tag.first().map(|n| n.as_str()) == Some(name) {
+            tag.get(1).cloned()
+        } else {
+            None
+        }
+    })
+}
+
+/// Checks the auth event's url against the request. Without a configured
+/// public url the host is compared against the one the request was sent to.
+fn url_matches(config: &Config, url: &str, headers: &HeaderMap, uri: &Uri) -> bool {
+    let path_and_query = uri.path_and_query().map_or(uri.path(), |p| p.as_str());
+    match config.public_url {
+        Some(ref public_url) => {
+            url == format!("{}{path_and_query}", public_url.trim_end_matches('/'))
+        }
+        None => match Url::parse(url) {
+            Ok(url) => {
+                let auth_path = match url.query() {
+                    Some(query) => format!("{}?{query}", url.path()),
+                    None => url.path().to_string(),
+                };
+                let auth_host = match (url.host_str(), url.port()) {
+                    (Some(host), Some(port)) => format!("{host}:{port}"),
+                    (Some(host), None) => host.to_string(),
+                    (None, _) => return false,
+                };
+                let host = uri
+                    .authority()
+                    .map(|a| a.as_str())
+                    .or_else(|| headers.get(HOST).and_then(|h| h.to_str().ok()));
+
+                matches!(url.scheme(), "http" | "https")
+                    && host.is_some_and(|h| h.eq_ignore_ascii_case(&auth_host))
+                    && auth_path == path_and_query
+            }
+            Err(_) => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use nostr::{EventBuilder, Keys, Kind, Tag};
+
+    const URL: &str = "https://api.example.com/add-sigs?id=1";
+    const BODY: &[u8] = b"{\"id\":1}";
+
+    fn config(public_url: Option<&str>) -> Config {
+        let mut args = vec![
+            "note-duel",
+            "--pg-url",
+            "postgres://",
+            "--events-db",
+            "events.db",
+        ];
+        if let Some(public_url) = public_url {
+            args.extend(["--public-url", public_url]);
+        }
+        Config::try_parse_from(args).unwrap()
+    }
+
+    fn auth_event(kind: u64, tags: &[(&str, &str)], created_at: u64) -> Event {
+        let tags = tags
+            .iter()
+            .map(|(name, value)| Tag::parse(vec![name.to_string(), value.to_string()]).unwrap())
+            .collect::<Vec<_>>();
+        EventBuilder::new(Kind::Custom(kind), "", tags)
+            .custom_created_at(Timestamp::from(created_at))
+            .to_event(&Keys::generate())
+            .unwrap()
+    }
+
+    fn valid_event(url: &str) -> Event {
+        let payload = sha256::Hash::hash(BODY).to_string();
+        let tags = [
+            ("u", url),
+            ("method", "POST"),
+            ("payload", payload.as_str()),
+        ];
+        auth_event(HTTP_AUTH_KIND, &tags, Timestamp::now().as_u64())
+    }
+
+    fn verify(config: &Config, event: &Event, method: Method, body: &[u8]) -> Result<(), String> {
+        let uri = Uri::from_static("/add-sigs?id=1");
+        let mut headers = HeaderMap::new();
+        headers.insert(HOST, "api.example.com".parse().unwrap());
+        match verify_auth_event(config, event, &headers, &method, &uri, Some(body)) {
+            Ok(pubkey) => {
+                assert_eq!(pubkey, event.pubkey);
+                Ok(())
+            }
+            Err(ApiError::Unauthorized(msg)) => Err(msg),
+            Err(e) => panic!("unexpected error {e:?}"),
+        }
+    }
+
+    #[test]
+    fn accepts_an_event_for_the_request() {
+        let config = config(Some("https://api.example.com/"));
+        assert_eq!(
+            verify(&config, &valid_event(URL), Method::POST, BODY),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_other_kinds() {
+        let config = config(Some("https://api.example.com"));
+        let tags = [("u", URL), ("method", "POST")];
+        let event = auth_event(1, &tags, Timestamp::now().as_u64());
+        assert_eq!(
+            verify(&config, &event, Method::POST, &[]),
+            Err("invalid auth event kind".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_a_wrong_url() {
+        let config = config(Some("https://api.example.com"));
+        for url in [
+            "https://api.example.com/add-sigs?id=2",
+            "https://api.example.com/accept-bet?id=1",
+            "https://evil.example.com/add-sigs?id=1",
+            "http://api.example.com/add-sigs?id=1",
+        ] {
+            assert_eq!(
+                verify(&config, &valid_event(url), Method::POST, BODY),
+                Err("auth event url does not match".to_string()),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn checks_the_host_without_a_public_url() {
+        let config = config(None);
+        assert_eq!(
+            verify(&config, &valid_event(URL), Method::POST, BODY),
+            Ok(())
+        );
+        for url in [
+            "https://evil.example.com/add-sigs?id=1",
+            "ftp://api.example.com/add-sigs?id=1",
+        ] {
+            assert_eq!(
+                verify(&config, &valid_event(url), Method::POST, BODY),
+                Err("auth event url does not match".to_string()),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_a_wrong_method() {
+        let config = config(Some("https://api.example.com"));
+        assert_eq!(
+            verify(&config, &valid_event(URL), Method::PUT, BODY),
+            Err("auth event method does not match".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_stale_events() {
+        let config = config(Some("https://api.example.com"));
+        let tags = [("u", URL), ("method", "POST")];
+        let now = Timestamp::now().as_u64();
+        for created_at in [
+            now - config.auth_window_secs - 5,
+            now + config.auth_window_secs + 5,
+        ] {
+            let event = auth_event(HTTP_AUTH_KIND, &tags, created_at);
+            assert_eq!(
+                verify(&config, &event, Method::POST, &[]),
+                Err("auth event expired".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_a_payload_mismatch() {
+        let config = config(Some("https://api.example.com"));
+        assert_eq!(
+            verify(&config, &valid_event(URL), Method::POST, b"{\"id\":2}"),
+            Err("auth event payload does not match".to_string())
+        );
+
+        let tags = [("u", URL), ("method", "POST")];
+        let event = auth_event(HTTP_AUTH_KIND, &tags, Timestamp::now().as_u64());
+        assert_eq!(
+            verify(&config, &event, Method::POST, BODY),
+            Err("auth event payload does not match".to_string())
+        );
+    }
+}
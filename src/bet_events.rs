@@ -0,0 +1,137 @@
+use crate::models::bet::Bet;
+use crate::models::bet_oracle::BetOracle;
+use crate::models::bet_participant::BetParticipant;
+use crate::models::user_settings::UserSettings;
+use crate::updates::Notification;
+use crate::State;
+use log::{debug, error, warn};
+use nostr::{EventBuilder, Kind, Tag, Timestamp};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::broadcast::error::RecvError;
+
+/// Kind of the parameterized replaceable events bets are published as
+pub const BET_EVENT_KIND: u64 = 30_089;
+
+/// Publishes a server-signed NIP-33 event for every bet, replacing it each
+/// time the bet is updated.
+pub async fn start_bet_events_task(state: State) {
+    let mut receiver = state.updates.subscribe();
+    // when each bet's event was last published, so a newer one always wins
+    let mut last_published = HashMap::new();
+    loop {
+        match receiver.recv().await {
+            Ok(notification) => {
+                let res = publish_bet_event(&state, &notification, &mut last_published).await;
+                if let Err(e) = res {
+                    error!(
+                        "Error publishing event for bet {}: {e}",
+                        notification.update.bet_id()
+                    );
+                }
+            }
+            Err(RecvError::Lagged(missed)) => warn!("Missed {missed} bet updates to publish"),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// The `created_at` for a bet's next event, past the one it replaces. Relays
+/// keep the replaceable event with the lowest id when two share a second, so
+/// updates published within the same second would be dropped at random.
+fn next_created_at(now: u64, last_published: Option<u64>) -> u64 {
+    last_published.map_or(now, |last| now.max(last + 1))
+}
+
+async fn publish_bet_event(
+    state: &State,
+    notification: &Notification,
+    last_published: &mut HashMap<i32, u64>,
+) -> anyhow::Result<()> {
+    let bet_id = notification.update.bet_id();
+    let mut tags = vec![Tag::parse(vec!["d".to_string(), bet_id.to_string()])?];
+
+    let mut conn = state.db_pool.get()?;
+    let Some(bet) = Bet::get_by_id(&mut conn, bet_id)? else {
+        return Ok(());
+    };
+
+    let participants = BetParticipant::get_by_bet_id(&mut conn, bet.id)?;
+    let mut private = HashSet::new();
+    for participant in participants.iter() {
+        if UserSettings::is_private(&mut conn, participant.pubkey())? {
+            private.insert(participant.pubkey());
+        }
+    }
+
+    tags.push(Tag::parse(vec![
+        "status".to_string(),
+        bet.status().as_str().to_string(),
+    ])?);
+    // the oracle events could be used to find a private participant's notes
+    if private.is_empty() {
+        for oracle in BetOracle::get_by_bet_id(&mut conn, bet.id)? {
+            tags.push(Tag::parse(vec![
+                "e".to_string(),
+                oracle.oracle_event_id().to_hex(),
+            ])?);
+        }
+    }
+    for participant in participants {
+        // their outcome notes are signed by them, so leave those out too
+        if private.contains(&participant.pubkey()) {
+            continue;
+        }
+        tags.push(Tag::parse(vec![
+            "p".to_string(),
+            participant.pubkey().to_string(),
+        ])?);
+        if let Some((id, note)) = participant
+            .outcome_event_id()
+            .zip(participant.published_note())
+        {
+            tags.push(Tag::parse(vec![
+                "outcome".to_string(),
+                id.to_hex(),
+                note.as_str().to_string(),
+            ])?);
+        }
+    }
+    drop(conn);
+
+    let now = Timestamp::now().as_u64();
+    let created_at = next_created_at(now, last_published.get(&bet_id).copied());
+    // older entries can't hold back any event anymore
+    last_published.retain(|_, last| *last >= now);
+    last_published.insert(bet_id, created_at);
+
+    let event = EventBuilder::new(Kind::Custom(BET_EVENT_KIND), "", tags)
+        .custom_created_at(Timestamp::from(created_at))
+        .to_event(&state.keys)?;
+    let event_id = state.nostr_client.send_event(event).await?;
+    debug!("Published event {event_id} for bet {bet_id}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_event_is_published_now() {
+        assert_eq!(next_created_at(1_000, None), 1_000);
+    }
+
+    #[test]
+    fn events_in_the_same_second_move_forward() {
+        let first = next_created_at(1_000, None);
+        let second = next_created_at(1_000, Some(first));
+        let third = next_created_at(1_000, Some(second));
+        assert_eq!((first, second, third), (1_000, 1_001, 1_002));
+    }
+
+    #[test]
+    fn later_events_are_published_now() {
+        assert_eq!(next_created_at(1_005, Some(1_002)), 1_005);
+    }
+}
@@ -0,0 +1,228 @@
+use async_trait::async_trait;
+use log::{debug, error};
+use nostr::{ClientMessage, Event, Keys};
+use nostr_sdk::{Client, ClientBuilder};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Somewhere signed outcome notes are published to, on top of the relays the
+/// listener is connected to.
+#[async_trait]
+pub trait Broadcaster: Send + Sync {
+    /// Name of the target, used in logs and stats
+    fn name(&self) -> String;
+
+    async fn broadcast(&self, event: &Event) -> anyhow::Result<()>;
+}
+
+/// Posts events to an HTTP endpoint, like blastr.
+pub struct HttpBroadcaster {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpBroadcaster {
+    pub fn new(client: reqwest::Client, url: String) -> Self {
+        Self { client, url }
+    }
+}
+
+#[async_trait]
+impl Broadcaster for HttpBroadcaster {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn broadcast(&self, event: &Event) -> anyhow::Result<()> {
+        let msg = ClientMessage::event(event.clone());
+        self.client
+            .post(&self.url)
+            .json(&msg)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Publishes events to a relay we don't read from.
+pub struct RelayBroadcaster {
+    client: Client,
+    url: String,
+}
+
+impl RelayBroadcaster {
+    pub async fn new(url: String, keys: &Keys) -> anyhow::Result<Self> {
+        let client = ClientBuilder::new().signer(keys).build();
+        client.add_relay(url.as_str()).await?;
+        client.connect().await;
+        Ok(Self { client, url })
+    }
+}
+
+#[async_trait]
+impl Broadcaster for RelayBroadcaster {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn broadcast(&self, event: &Event) -> anyhow::Result<()> {
+        self.client.send_event(event.clone()).await?;
+        Ok(())
+    }
+}
+
+/// The relays a client is already connected to, like the listener's.
+#[async_trait]
+impl Broadcaster for Client {
+    fn name(&self) -> String {
+        "connected relays".to_string()
+    }
+
+    async fn broadcast(&self, event: &Event) -> anyhow::Result<()> {
+        self.send_event(event.clone()).await?;
+        Ok(())
+    }
+}
+
+struct Target {
+    broadcaster: Box<dyn Broadcaster>,
+    sent: AtomicU64,
+    failed: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastStats {
+    name: String,
+    sent: u64,
+    failed: u64,
+}
+
+/// Sends events to every configured broadcaster, keeping count of how many
+/// were sent and how many failed for each.
+#[derive(Default)]
+pub struct Broadcasters {
+    targets: Vec<Target>,
+}
+
+impl Broadcasters {
+    pub fn new(broadcasters: Vec<Box<dyn Broadcaster>>) -> Self {
+        let targets = broadcasters
+            .into_iter()
+            .map(|broadcaster| Target {
+                broadcaster,
+                sent: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+            })
+            .collect();
+        Self { targets }
+    }
+
+    pub async fn broadcast(&self, event: &Event) {
+        for target in self.targets.iter() {
+            match target.broadcaster.broadcast(event).await {
+                Ok(_) => {
+                    target.sent.fetch_add(1, Ordering::Relaxed);
+                    debug!(
+                        "Broadcast event {} to {}",
+                        event.id,
+                        target.broadcaster.name()
+                    );
+                }
+                Err(e) => {
+                    target.failed.fetch_add(1, Ordering::Relaxed);
+                    error!(
+                        "Error broadcasting event {} to {}: {e}",
+                        event.id,
+                        target.broadcaster.name()
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn stats(&self) -> Vec<BroadcastStats> {
+        self.targets
+            .iter()
+            .map(|t| BroadcastStats {
+                name: t.broadcaster.name(),
+                sent: t.sent.load(Ordering::Relaxed),
+                failed: t.failed.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr::{EventBuilder, EventId, Kind};
+    use std::sync::{Arc, Mutex};
+
+    /// Records the events it's given, or fails to send any of them.
+    struct RecordingBroadcaster {
+        name: String,
+        sent: Arc<Mutex<Vec<EventId>>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Broadcaster for RecordingBroadcaster {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        async fn broadcast(&self, event: &Event) -> anyhow::Result<()> {
+            if self.fail {
+                anyhow::bail!("target is down");
+            }
+            self.sent.lock().unwrap().push(event.id);
+            Ok(())
+        }
+    }
+
+    fn recording(name: &str, fail: bool) -> (Box<dyn Broadcaster>, Arc<Mutex<Vec<EventId>>>) {
+        let sent = Arc::new(Mutex::new(vec![]));
+        let broadcaster = RecordingBroadcaster {
+            name: name.to_string(),
+            sent: sent.clone(),
+            fail,
+        };
+        (Box::new(broadcaster), sent)
+    }
+
+    fn note() -> Event {
+        EventBuilder::new(Kind::TextNote, "outcome", Vec::new())
+            .to_event(&Keys::generate())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn broadcasts_to_every_target() {
+        let (first, first_sent) = recording("first", false);
+        let (second, second_sent) = recording("second", false);
+        let broadcasters = Broadcasters::new(vec![first, second]);
+
+        let event = note();
+        broadcasters.broadcast(&event).await;
+
+        assert_eq!(*first_sent.lock().unwrap(), vec![event.id]);
+        assert_eq!(*second_sent.lock().unwrap(), vec![event.id]);
+    }
+
+    #[tokio::test]
+    async fn failures_are_counted() {
+        let (working, sent) = recording("working", false);
+        let (down, _) = recording("down", true);
+        let broadcasters = Broadcasters::new(vec![working, down]);
+
+        broadcasters.broadcast(&note()).await;
+        broadcasters.broadcast(&note()).await;
+
+        let stats = broadcasters.stats();
+        assert_eq!(sent.lock().unwrap().len(), 2);
+        assert_eq!((stats[0].sent, stats[0].failed), (2, 0));
+        assert_eq!(stats[1].name, "down");
+        assert_eq!((stats[1].sent, stats[1].failed), (0, 2));
+    }
+}
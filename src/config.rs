@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use nostr::key::XOnlyPublicKey;
 
 #[derive(Parser, Debug, Clone)]
 #[command(version, author, about)]
@@ -19,4 +20,146 @@ pub struct Config {
     #[clap(default_value_t = 3000, long)]
     /// Port for note-duel's webserver
     pub port: u16,
+    #[clap(default_value = "https://nostr.mutinywallet.com", long)]
+    /// HTTP endpoint to also post signed outcome notes to, like blastr, can be specified multiple times
+    pub broadcast_url: Vec<String>,
+    #[clap(long, conflicts_with = "broadcast_url")]
+    /// Don't post signed outcome notes to any HTTP endpoint, not even the default one
+    pub no_broadcast: bool,
+    #[clap(long)]
+    /// Relay to also publish signed outcome notes to without listening on it, can be specified multiple times
+    pub publish_relay: Vec<String>,
+    #[clap(default_value_t = 604_800, long)]
+    /// Seconds a bet proposal stays open when the proposer doesn't set a deadline
+    pub proposal_expiry_secs: u64,
+    #[clap(default_value_t = 604_800, long)]
+    /// Seconds after an oracle event matures before an unattested bet is voided
+    pub void_grace_period_secs: u64,
+    #[clap(long)]
+    /// Public URL of the webserver, used to check the URL of NIP-98 auth events. Without it the request's Host header is used
+    pub public_url: Option<String>,
+    #[clap(default_value_t = 60, long)]
+    /// How many seconds a NIP-98 auth event is valid for
+    pub auth_window_secs: u64,
+    #[clap(value_enum, default_value_t = UnauthenticatedListing::Redacted, long)]
+    /// What unauthenticated callers see when listing a user's bets
+    pub unauthenticated_listing: UnauthenticatedListing,
+    #[clap(default_values_t = [1], long)]
+    /// Event kind allowed for outcome notes, can be specified multiple times
+    pub note_kind: Vec<u64>,
+    #[clap(default_value_t = 2_000, long)]
+    /// Maximum length in bytes of an outcome note's content
+    pub max_note_content_len: usize,
+    #[clap(default_value_t = 600, long)]
+    /// How many seconds in the future an outcome note's created_at can be
+    pub max_note_future_secs: u64,
+    #[clap(default_value_t = 86_400, long)]
+    /// How many seconds in the past an outcome note's created_at can be
+    pub max_note_age_secs: u64,
+    #[clap(default_value_t = 10, long)]
+    /// Maximum number of participants in a bet, including the proposer
+    pub max_participants: usize,
+    #[clap(default_value_t = 5, long)]
+    /// Maximum number of oracles a bet can be settled by
+    pub max_oracles: usize,
+    #[clap(long)]
+    /// Accept bets whose oracle announcement event can't be found, marking them unverified
+    pub allow_unverified_announcements: bool,
+    #[clap(default_value_t = 10, long)]
+    /// Seconds to wait for relays when fetching an oracle announcement event
+    pub announcement_fetch_timeout_secs: u64,
+    #[clap(value_enum, default_value_t = OraclePolicy::Any, long)]
+    /// Which oracles bets can be created with
+    pub oracle_policy: OraclePolicy,
+    #[clap(long)]
+    /// Pubkey allowed to manage the oracle registry, can be specified multiple times
+    pub admin_pubkey: Vec<XOnlyPublicKey>,
+    #[clap(long, env = "NOTE_DUEL_SECRET_KEY", hide_env_values = true)]
+    /// The server's nostr secret key, as hex or nsec, instead of using the keys file
+    pub secret_key: Option<String>,
+    #[clap(default_value = "server.key", long)]
+    /// File with the server's nostr secret key, generated on first run
+    pub keys_file: String,
+    #[clap(long)]
+    /// Don't send participants DM notifications about their bets
+    pub disable_dm_notifications: bool,
+    #[clap(long)]
+    /// Don't publish bets as nostr events
+    pub disable_bet_events: bool,
+}
+
+impl Config {
+    /// The HTTP endpoints signed outcome notes are posted to.
+    pub fn broadcast_urls(&self) -> &[String] {
+        match self.no_broadcast {
+            true => &[],
+            false => &self.broadcast_url,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnauthenticatedListing {
+    /// Bets are listed without the contents of the outcome notes
+    Redacted,
+    /// Listing bets requires authentication
+    Denied,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OraclePolicy {
+    /// Any oracle can be used
+    Any,
+    /// Only oracles trusted in the registry can be used
+    Allowlist,
+    /// Any oracle that isn't blocked in the registry can be used
+    Blocklist,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn try_parse(args: &[&str]) -> Result<Config, clap::Error> {
+        let required = [
+            "note-duel",
+            "--pg-url",
+            "postgres://",
+            "--events-db",
+            "events.db",
+        ];
+        Config::try_parse_from(required.iter().chain(args))
+    }
+
+    fn parse(args: &[&str]) -> Config {
+        try_parse(args).unwrap()
+    }
+
+    #[test]
+    fn broadcasts_to_the_default_url() {
+        let config = parse(&[]);
+        assert_eq!(config.broadcast_urls(), ["https://nostr.mutinywallet.com"]);
+    }
+
+    #[test]
+    fn broadcast_urls_replace_the_default() {
+        let config = parse(&[
+            "--broadcast-url",
+            "https://a",
+            "--broadcast-url",
+            "https://b",
+        ]);
+        assert_eq!(config.broadcast_urls(), ["https://a", "https://b"]);
+    }
+
+    #[test]
+    fn broadcasting_can_be_turned_off() {
+        let config = parse(&["--no-broadcast"]);
+        assert!(config.broadcast_urls().is_empty());
+    }
+
+    #[test]
+    fn no_broadcast_conflicts_with_broadcast_urls() {
+        assert!(try_parse(&["--no-broadcast", "--broadcast-url", "https://a"]).is_err());
+    }
 }
@@ -0,0 +1,113 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use log::error;
+use serde::Serialize;
+use std::fmt;
+
+/// Errors returned by the API, each with a stable machine-readable code.
+///
+/// Request handlers work with [`anyhow::Error`], raising these where the
+/// cause is known. Anything else is reported as an internal error.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request is malformed or has invalid parameters
+    BadRequest(String),
+    /// A signature or event id in the request does not verify
+    InvalidSignature(String),
+    /// The request is missing valid authentication
+    Unauthorized(String),
+    /// The caller is not allowed to make the request
+    Forbidden(String),
+    /// The bet or oracle does not exist
+    NotFound(String),
+    /// The bet is not in a state that allows the request
+    Conflict(String),
+    /// The adaptor sigs don't cover the bet's outcomes
+    InvalidSigs(String),
+    /// The oracle event type is not supported
+    UnsupportedEvent(String),
+    /// Something went wrong on our end
+    Internal(anyhow::Error),
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::InvalidSignature(_) => "invalid_signature",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::InvalidSigs(_) => "invalid_sigs",
+            ApiError::UnsupportedEvent(_) => "unsupported_event",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) | ApiError::InvalidSignature(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::InvalidSigs(_) | ApiError::UnsupportedEvent(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(msg)
+            | ApiError::InvalidSignature(msg)
+            | ApiError::Unauthorized(msg)
+            | ApiError::Forbidden(msg)
+            | ApiError::NotFound(msg)
+            | ApiError::Conflict(msg)
+            | ApiError::InvalidSigs(msg)
+            | ApiError::UnsupportedEvent(msg) => write!(f, "{msg}"),
+            ApiError::Internal(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<ApiError>() {
+            Ok(e) => e,
+            Err(e) => ApiError::Internal(e),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let message = match self {
+            ApiError::Internal(ref e) => {
+                error!("Internal error: {e:?}");
+                "internal error".to_string()
+            }
+            _ => self.to_string(),
+        };
+        let body = ErrorBody {
+            error: self.code(),
+            message,
+        };
+
+        (self.status(), Json(body)).into_response()
+    }
+}
@@ -0,0 +1,170 @@
+use crate::listener;
+use crate::models::bet::Bet;
+use crate::models::bet_oracle::BetOracle;
+use crate::models::bet_participant::BetParticipant;
+use crate::models::used_event::UsedEvent;
+use crate::updates::{self, BetUpdate};
+use crate::State;
+use diesel::PgConnection;
+use log::{error, info, warn};
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// How long a settlement can run before it's considered interrupted, well
+/// past the listener's timeout for handling an attestation
+const STALE_SETTLEMENT_SECS: i64 = 600;
+
+/// Periodically expires bet proposals that were not answered in time, voids
+/// bets the oracle never attested to, finishes interrupted settlements and
+/// forgets stale used events.
+pub async fn start_expiry_task(state: State) {
+    let duration = Duration::from_secs(60);
+    loop {
+        if let Err(e) = expire_proposals(&state) {
+            error!("Error expiring proposals: {e}");
+        }
+
+        if let Err(e) = void_unattested_bets(&state).await {
+            error!("Error voiding bets: {e}");
+        }
+
+        if let Err(e) = recover_stale_settlements(&state).await {
+            error!("Error recovering settlements: {e}");
+        }
+
+        if let Err(e) = prune_used_events(&state) {
+            error!("Error pruning used events: {e}");
+        }
+
+        sleep(duration).await;
+    }
+}
+
+fn expire_proposals(state: &State) -> anyhow::Result<()> {
+    let mut conn = state.db_pool.get()?;
+    let now = chrono::Utc::now().naive_utc();
+
+    for bet in Bet::get_open_proposals(&mut conn)? {
+        if bet.proposal_deadline() <= now {
+            // the bet may have been accepted in the meantime
+            if let Err(e) = Bet::set_expired(&mut conn, bet.id, now) {
+                warn!("Could not expire bet proposal {}: {e}", bet.id);
+                continue;
+            }
+            info!("Expired bet proposal {}", bet.id);
+            updates::notify_bet(state, &mut conn, BetUpdate::Expired { bet_id: bet.id });
+        }
+    }
+
+    Ok(())
+}
+
+async fn void_unattested_bets(state: &State) -> anyhow::Result<()> {
+    let mut conn = state.db_pool.get()?;
+    let now = chrono::Utc::now().naive_utc();
+    let grace_period = chrono::Duration::seconds(state.config.void_grace_period_secs as i64);
+
+    let mut voided = false;
+    for bet in Bet::get_unsettled_bets(&mut conn)? {
+        // wait for the last of the oracles to mature
+        let maturity = BetOracle::get_by_bet_id(&mut conn, bet.id)?
+            .iter()
+            .map(|o| o.oracle_announcement().oracle_event.event_maturity_epoch)
+            .max()
+            .unwrap_or(bet.oracle_announcement().oracle_event.event_maturity_epoch);
+        let Some(maturity) = chrono::NaiveDateTime::from_timestamp_opt(maturity as i64, 0) else {
+            continue;
+        };
+
+        if maturity + grace_period <= now {
+            // the bet may have started settling in the meantime
+            if let Err(e) = Bet::set_voided(&mut conn, bet.id, now) {
+                warn!("Could not void bet {}: {e}", bet.id);
+                continue;
+            }
+            warn!(
+                "Voided bet {}, oracle never attested, outcome notes will not be published",
+                bet.id
+            );
+            updates::notify_bet(state, &mut conn, BetUpdate::Voided { bet_id: bet.id });
+            voided = true;
+        }
+    }
+
+    // stop listening for the voided bets' oracle events
+    if voided {
+        refresh_event_channel(state, &mut conn).await?;
+    }
+
+    Ok(())
+}
+
+/// Finishes bets whose settlement was interrupted, so they don't stay
+/// settling forever. Bets with their outcome notes recorded end the way the
+/// notes say, the rest fail.
+async fn recover_stale_settlements(state: &State) -> anyhow::Result<()> {
+    let mut conn = state.db_pool.get()?;
+    let cutoff = chrono::Utc::now().naive_utc() - chrono::Duration::seconds(STALE_SETTLEMENT_SECS);
+
+    let mut recovered = false;
+    for bet in Bet::get_stale_settling_bets(&mut conn, cutoff)? {
+        let published = BetParticipant::get_by_bet_id(&mut conn, bet.id)?
+            .iter()
+            .filter_map(|p| p.published_note().zip(p.outcome_event_id()))
+            .collect::<Vec<_>>();
+
+        if published.is_empty() {
+            let details = "Settlement was interrupted before the outcome notes were recorded";
+            if let Err(e) = Bet::set_settlement_error(&mut conn, bet.id, details) {
+                warn!("Could not fail bet {}: {e}", bet.id);
+                continue;
+            }
+            warn!("Failed bet {}: {details}", bet.id);
+        } else {
+            let (status, update) = listener::settlement(bet.id, &published);
+            if let Err(e) = Bet::set_status(&mut conn, bet.id, status) {
+                warn!("Could not finish settling bet {}: {e}", bet.id);
+                continue;
+            }
+            warn!(
+                "Finished interrupted settlement of bet {}, some outcome notes may not have been published",
+                bet.id
+            );
+            updates::notify_bet(state, &mut conn, update);
+        }
+        recovered = true;
+    }
+
+    if recovered {
+        refresh_event_channel(state, &mut conn).await?;
+    }
+
+    Ok(())
+}
+
+/// Points the listener at the oracle events of the bets still waiting on them.
+async fn refresh_event_channel(state: &State, conn: &mut PgConnection) -> anyhow::Result<()> {
+    let event_ids = Bet::get_unfinished_bets(conn)?;
+    let sender = state.event_channel.lock().await;
+    sender.send_if_modified(|current| {
+        if *current == event_ids {
+            false
+        } else {
+            *current = event_ids;
+            true
+        }
+    });
+    Ok(())
+}
+
+fn prune_used_events(state: &State) -> anyhow::Result<()> {
+    let mut conn = state.db_pool.get()?;
+
+    // signed events are only accepted within the auth window, keep them
+    // around well past that
+    let retention = (state.config.auth_window_secs * 3).max(86_400);
+    let cutoff = chrono::Utc::now().naive_utc() - chrono::Duration::seconds(retention as i64);
+    UsedEvent::delete_before(&mut conn, cutoff)?;
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use crate::config::Config;
+use log::info;
+use nostr::Keys;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+/// Loads the server's nostr keys, from the configured secret key if there is
+/// one, otherwise from the keys file.
+pub fn load(config: &Config) -> anyhow::Result<Keys> {
+    match config.secret_key {
+        Some(ref secret) => Ok(Keys::from_sk_str(secret.trim())?),
+        None => load_or_generate(&config.keys_file),
+    }
+}
+
+/// Loads the server's nostr keys from the file, generating and saving new
+/// ones if it doesn't exist yet.
+fn load_or_generate(path: &str) -> anyhow::Result<Keys> {
+    if Path::new(path).exists() {
+        let secret = fs::read_to_string(path)?;
+        let keys = Keys::from_sk_str(secret.trim())?;
+        return Ok(keys);
+    }
+
+    let keys = Keys::generate();
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    writeln!(file, "{}", keys.secret_key()?.display_secret())?;
+    info!("Generated new server keys at {path}");
+
+    Ok(keys)
+}
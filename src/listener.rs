@@ -1,18 +1,27 @@
-use crate::models::bet::Bet;
-use crate::models::sig::Sig;
+use crate::broadcast::{Broadcaster, Broadcasters};
+use crate::error::ApiError;
+use crate::models::attestation::Attestation;
+use crate::models::bet::{Bet, BetStatus};
+use crate::models::bet_oracle::BetOracle;
+use crate::models::bet_participant::BetParticipant;
+use crate::models::rejected_attestation::RejectedAttestation;
+use crate::models::sig::{Sig, SignedNote};
+use crate::updates::{self, BetUpdate};
 use crate::utils::oracle_attestation_from_str;
-use crate::State;
+use crate::{numeric, utils, State};
 use anyhow::anyhow;
-use diesel::PgConnection;
-use dlc_messages::oracle_msgs::OracleAttestation;
+use diesel::{Connection, PgConnection};
+use dlc::secp256k1_zkp::SecretKey;
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation};
 use log::{debug, error, info, warn};
-use nostr::{ClientMessage, Event, EventId, Filter, Keys, Kind, Tag};
+use nostr::{Event, EventId, Filter, Kind, Tag, UnsignedEvent};
 use nostr_sdk::{Client, ClientBuilder, RelayPoolNotification};
 use nostr_sqlite::SQLiteDatabase;
 use schnorr_fun::adaptor::Adaptor;
-use schnorr_fun::fun::marker::Public;
-use schnorr_fun::fun::Scalar;
-use std::collections::HashSet;
+use schnorr_fun::fun::marker::{EvenY, NonZero, Public};
+use schnorr_fun::fun::{Point, Scalar};
+use schnorr_fun::Message;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use tokio::sync::watch::Receiver;
 
@@ -24,12 +33,9 @@ pub async fn start_listener(
 ) -> anyhow::Result<()> {
     debug!("Using relays: {:?}", relays);
 
-    let blastr = reqwest::Client::new();
-
-    let keys = Keys::generate();
     loop {
         let client = ClientBuilder::new()
-            .signer(&keys)
+            .signer(&state.keys)
             .database(listener_db.clone())
             .build();
         client.add_relays(relays.clone()).await?;
@@ -55,13 +61,11 @@ pub async fn start_listener(
                             if event.kind.as_u64() == 89 && event.verify().is_ok() {
                                 let state_clone = state.clone();
                                 let client_clone = client.clone();
-                                let blastr_clone = blastr.clone();
                                 tokio::spawn({
                                     async move {
                                         let fut = handle_event(
                                             state_clone,
                                             client_clone,
-                                            &blastr_clone,
                                             event,
                                         );
 
@@ -93,12 +97,7 @@ pub async fn start_listener(
     }
 }
 
-async fn handle_event(
-    state: State,
-    client: Client,
-    blastr: &reqwest::Client,
-    event: Event,
-) -> anyhow::Result<()> {
+async fn handle_event(state: State, client: Client, event: Event) -> anyhow::Result<()> {
     let e_tag = event.tags.iter().find_map(|t| {
         if let Tag::Event { event_id, .. } = t {
             Some(*event_id)
@@ -111,10 +110,50 @@ async fn handle_event(
     let attestation = oracle_attestation_from_str(&event.content)?;
 
     let mut conn = state.db_pool.get()?;
+
+    // only keep attestations that match what the oracle announced, each bet
+    // has its own copy of the announcement
+    let mut seen = HashSet::new();
+    let announcements = BetOracle::get_by_oracle_event(&mut conn, &e_tag)?
+        .into_iter()
+        .map(|o| o.oracle_announcement())
+        .filter(|a| {
+            seen.insert((
+                a.oracle_public_key.serialize(),
+                a.oracle_event.event_id.clone(),
+            ))
+        })
+        .collect::<Vec<_>>();
+    let mut verified = false;
+    for announcement in announcements.iter() {
+        match utils::verify_attestation(&state.secp, announcement, &attestation) {
+            Ok(_) => verified = true,
+            Err(e) => {
+                warn!("Rejected attestation {} for {e_tag}: {e}", event.id);
+                RejectedAttestation::create(&mut conn, &event, e_tag, &e.to_string())?;
+            }
+        }
+    }
+    if !verified {
+        return Err(anyhow!(
+            "No valid announcement for attestation {}",
+            event.id
+        ));
+    }
+
+    Attestation::create(&mut conn, e_tag, &attestation)?;
     let bets = Bet::get_by_oracle_event(&mut conn, &e_tag)?;
 
     for bet in bets {
-        if let Err(e) = handle_bet(&mut conn, &state, &client, blastr, &attestation, bet).await {
+        updates::notify_bet(
+            &state,
+            &mut conn,
+            BetUpdate::Attested {
+                bet_id: bet.id,
+                oracle_event_id: e_tag,
+            },
+        );
+        if let Err(e) = handle_bet(&mut conn, &state, &client, bet).await {
             error!("Error handling bet: {e}");
         }
     }
@@ -122,103 +161,380 @@ async fn handle_event(
     Ok(())
 }
 
+/// Finds the outcome the oracle attested to, as the bet's sigs are keyed, and
+/// how many of its nonces were used to sign it.
+fn attested_outcome(
+    announcement: &OracleAnnouncement,
+    attestation: &OracleAttestation,
+    outcomes: &HashSet<String>,
+) -> anyhow::Result<(String, usize)> {
+    let (outcome, nb_nonces) = match announcement.oracle_event.event_descriptor {
+        EventDescriptor::EnumEvent(_) => (
+            attestation
+                .outcomes
+                .first()
+                .ok_or(anyhow!("No outcomes"))?
+                .clone(),
+            1,
+        ),
+        EventDescriptor::DigitDecompositionEvent(_) => {
+            // find the prefix the attested digits fall under
+            numeric::find_attested_prefix(outcomes, &attestation.outcomes)
+                .map(|(o, len)| (o.clone(), len))
+                .ok_or(anyhow!("No prefix matches attested outcome"))?
+        }
+    };
+    if attestation.signatures.len() < nb_nonces {
+        return Err(anyhow!("Not enough attestation signatures"));
+    }
+
+    Ok((outcome, nb_nonces))
+}
+
 async fn handle_bet(
     conn: &mut PgConnection,
     state: &State,
     client: &Client,
-    blastr: &reqwest::Client,
-    attestation: &OracleAttestation,
     bet: Bet,
 ) -> anyhow::Result<()> {
-    let outcome = attestation.outcomes.first().ok_or(anyhow!("No outcomes"))?;
-    let sig_a = Sig::get_by_params(conn, bet.id, outcome, true)?;
-    let sig_b = Sig::get_by_params(conn, bet.id, outcome, false)?;
+    let oracles = BetOracle::get_by_bet_id(conn, bet.id)?;
+    let event_ids = oracles
+        .iter()
+        .map(|o| o.oracle_event_id())
+        .collect::<Vec<_>>();
+    let attestations = Attestation::get_by_oracle_events(conn, &event_ids)?
+        .into_iter()
+        .map(|a| (a.oracle_event_id(), a.attestation()))
+        .collect::<HashMap<_, _>>();
+    let outcomes = Sig::get_by_bet_id(conn, bet.id)?
+        .into_iter()
+        .map(|s| s.outcome)
+        .collect::<HashSet<_>>();
+
+    // group the oracles that have attested by the outcome they attested to
+    let mut attested: HashMap<(String, usize), Vec<usize>> = HashMap::new();
+    for (index, oracle) in oracles.iter().enumerate() {
+        let Some(attestation) = attestations.get(&oracle.oracle_event_id()) else {
+            continue;
+        };
+        let announcement = oracle.oracle_announcement();
+        if let Err(e) = utils::verify_attestation(&state.secp, &announcement, attestation) {
+            warn!("Invalid attestation for bet {}: {e}", bet.id);
+            continue;
+        }
+        match attested_outcome(&announcement, attestation, &outcomes) {
+            Ok(outcome) => attested.entry(outcome).or_default().push(index),
+            Err(e) => warn!("Invalid attestation for bet {}: {e}", bet.id),
+        }
+    }
+
+    if !attested.is_empty() && bet.status() == BetStatus::Active {
+        Bet::set_status(conn, bet.id, BetStatus::Attested)?;
+    }
 
-    if sig_a.is_none() && sig_b.is_none() {
-        Bet::set_win_outcome_event_id(conn, bet.id, EventId::all_zeros())?; // if no sig, set outcome to 0s
-        Bet::set_lose_outcome_event_id(conn, bet.id, EventId::all_zeros())?; // if no sig, set outcome to 0s
+    let threshold = bet.threshold as usize;
+    let mut agreed = attested
+        .into_iter()
+        .filter(|(_, i)| i.len() >= threshold)
+        .collect::<Vec<_>>();
+    // only possible for bets created before thresholds had to be a majority
+    if agreed.len() > 1 {
+        Bet::set_status(conn, bet.id, BetStatus::NoContest)?;
+        updates::notify_bet(state, conn, BetUpdate::NoContest { bet_id: bet.id });
+        return Ok(warn!(
+            "Oracles reached the threshold on different outcomes for bet {}",
+            bet.id
+        ));
+    }
+    let Some(((outcome, nb_nonces), indexes)) = agreed.pop() else {
+        return Ok(info!("Waiting for more attestations for bet {}", bet.id));
+    };
+
+    if bet.no_contest_outcomes.contains(&outcome) {
+        Bet::set_status(conn, bet.id, BetStatus::NoContest)?;
+        updates::notify_bet(state, conn, BetUpdate::NoContest { bet_id: bet.id });
+        return Ok(info!("Bet {} ended with no contest on {outcome}", bet.id));
+    }
+    let is_draw = bet.draw_outcomes.contains(&outcome);
+
+    let combination = indexes[..threshold].to_vec();
+    let oracle_combination = utils::combinations(oracles.len(), threshold)
+        .iter()
+        .position(|c| *c == combination)
+        .ok_or(anyhow!("Invalid oracle combination"))? as i32;
+    let signatures = combination
+        .iter()
+        .map(|i| attestations[&oracles[*i].oracle_event_id()].signatures[..nb_nonces].to_vec())
+        .collect::<Vec<_>>();
+    let secret = dlc::signatures_to_secret(&signatures)?;
+
+    let participants = BetParticipant::get_by_bet_id(conn, bet.id)?;
+    let mut sigs = Vec::with_capacity(participants.len());
+    for participant in participants {
+        let sig = Sig::get_by_params(
+            conn,
+            bet.id,
+            &outcome,
+            participant.participant_index,
+            oracle_combination,
+        )?;
+        sigs.push((participant, sig));
+    }
+
+    if sigs.iter().all(|(_, sig)| sig.is_none()) {
+        // draws without draw notes have nothing to publish
+        if is_draw {
+            Bet::set_status(conn, bet.id, BetStatus::Drawn)?;
+            updates::notify_bet(
+                state,
+                conn,
+                BetUpdate::Drawn {
+                    bet_id: bet.id,
+                    draw_outcome_event_ids: vec![],
+                },
+            );
+            return Ok(info!("Bet {} ended in a draw on {outcome}", bet.id));
+        }
+        Bet::set_status(conn, bet.id, BetStatus::NoContest)?;
+        updates::notify_bet(state, conn, BetUpdate::NoContest { bet_id: bet.id });
         return Ok(warn!("No sigs found for event"));
     }
 
-    match sig_a {
-        None => warn!("Sig A not found!"),
-        Some(sig) => {
-            let (_, s_value) = dlc::secp_utils::schnorrsig_decompose(&attestation.signatures[0])?;
+    // attestations are handled concurrently, only one of them gets to settle
+    match Bet::set_status(conn, bet.id, BetStatus::Settling) {
+        Ok(_) => {}
+        Err(e) if matches!(e.downcast_ref::<ApiError>(), Some(ApiError::Conflict(_))) => {
+            return Ok(info!("Not settling bet {}: {e}", bet.id));
+        }
+        Err(e) => return Err(e),
+    }
+
+    // sign every note before publishing any of them
+    let mut notes = Vec::with_capacity(sigs.len());
+    for (participant, sig) in sigs {
+        let index = participant.participant_index;
+        match sig {
+            None => warn!("Sig for participant {index} not found!"),
+            Some(sig) => match decrypt_note(state, &secret, &sig, &participant) {
+                Ok(note) => notes.push((participant, sig.note(), note)),
+                Err(e) => {
+                    let details = format!("Sig for participant {index}: {e}");
+                    Bet::set_settlement_error(conn, bet.id, &details)?;
+                    return Err(anyhow!("Settlement failed for bet {}: {details}", bet.id));
+                }
+            },
+        }
+    }
 
-            let scalar: Scalar<Public> = Scalar::from_slice(s_value)
-                .ok_or(anyhow!("invalid scalar"))?
-                .non_zero()
-                .ok_or(anyhow!("zero scalar"))?;
+    // record the outcome notes before publishing them, so the bet settles
+    // even if some of them never make it out
+    let recorded = conn.transaction(|conn| {
+        for (participant, _, signed_event) in notes.iter() {
+            BetParticipant::set_outcome_event_id(conn, participant.id, signed_event.id)?;
+        }
+        anyhow::Ok(())
+    });
+    if let Err(e) = recorded {
+        let details = format!("Could not record outcome notes: {e}");
+        Bet::set_settlement_error(conn, bet.id, &details)?;
+        return Err(anyhow!("Settlement failed for bet {}: {details}", bet.id));
+    }
 
-            let valid_sig = state.schnorr.decrypt_signature(scalar, sig.sig());
+    let events = notes.iter().map(|(_, _, e)| e.clone()).collect::<Vec<_>>();
+    let failed = publish_notes(client, &state.broadcasters, &events).await;
+    if failed > 0 {
+        warn!(
+            "{failed} outcome notes for bet {} did not reach the relays",
+            bet.id
+        );
+    }
 
-            let unsigned = if sig.is_win {
-                bet.win_a()
-            } else {
-                bet.lose_a()
-            };
+    let published = notes
+        .iter()
+        .map(|(_, note, e)| (*note, e.id))
+        .collect::<Vec<_>>();
+    let (status, update) = settlement(bet.id, &published);
+    Bet::set_status(conn, bet.id, status)?;
+    updates::notify_bet(state, conn, update);
 
-            let signature =
-                nostr::secp256k1::schnorr::Signature::from_slice(&valid_sig.to_bytes())?;
-            let signed_event = unsigned.add_signature(signature)?;
+    Ok(())
+}
 
-            if sig.is_win {
-                Bet::set_win_outcome_event_id(conn, bet.id, signed_event.id)?;
-            } else {
-                Bet::set_lose_outcome_event_id(conn, bet.id, signed_event.id)?;
+/// Publishes the signed outcome notes to the listener's relays and every
+/// broadcaster, returning how many of them the relays didn't take. A note
+/// that fails doesn't stop the others from being published.
+async fn publish_notes(
+    relays: &dyn Broadcaster,
+    broadcasters: &Broadcasters,
+    notes: &[Event],
+) -> usize {
+    let mut failed = 0;
+    for note in notes {
+        broadcasters.broadcast(note).await;
+        match relays.broadcast(note).await {
+            Ok(_) => info!("Sent event with id: {}", note.id),
+            Err(e) => {
+                error!("Error publishing outcome note {}: {e}", note.id);
+                failed += 1;
             }
-
-            let msg = ClientMessage::event(signed_event.clone());
-            blastr
-                .post("https://nostr.mutinywallet.com")
-                .json(&msg)
-                .send()
-                .await
-                .ok();
-            let event_id = client.send_event(signed_event).await?;
-            info!("Sent event with id: {event_id}")
         }
     }
+    failed
+}
 
-    match sig_b {
-        None => warn!("Sig B not found!"),
-        Some(sig) => {
-            let (_, s_value) = dlc::secp_utils::schnorrsig_decompose(&attestation.signatures[0])?;
+/// The status a settled bet ends up in and the update sent about it, from
+/// the outcome notes recorded for it. Draw outcomes only have draw notes.
+pub fn settlement(bet_id: i32, published: &[(SignedNote, EventId)]) -> (BetStatus, BetUpdate) {
+    let ids = |note: SignedNote| {
+        published
+            .iter()
+            .filter(|(n, _)| *n == note)
+            .map(|(_, id)| *id)
+            .collect::<Vec<_>>()
+    };
+
+    let draw_outcome_event_ids = ids(SignedNote::Draw);
+    if !draw_outcome_event_ids.is_empty() {
+        let update = BetUpdate::Drawn {
+            bet_id,
+            draw_outcome_event_ids,
+        };
+        return (BetStatus::Drawn, update);
+    }
 
-            let scalar: Scalar<Public> = Scalar::from_slice(s_value)
-                .ok_or(anyhow!("invalid scalar"))?
-                .non_zero()
-                .ok_or(anyhow!("zero scalar"))?;
+    let update = BetUpdate::Settled {
+        bet_id,
+        win_outcome_event_id: ids(SignedNote::Win).first().copied(),
+        lose_outcome_event_ids: ids(SignedNote::Lose),
+    };
+    (BetStatus::Settled, update)
+}
 
-            let valid_sig = state.schnorr.decrypt_signature(scalar, sig.sig());
+/// Decrypts the adaptor sig into the participant's signature on the outcome
+/// note it was made for, making sure the result is valid for that note.
+fn decrypt_note(
+    state: &State,
+    secret: &SecretKey,
+    sig: &Sig,
+    participant: &BetParticipant,
+) -> anyhow::Result<Event> {
+    let scalar: Scalar<Public> = Scalar::from_slice(&secret.secret_bytes())
+        .ok_or(anyhow!("invalid scalar"))?
+        .non_zero()
+        .ok_or(anyhow!("zero scalar"))?;
+
+    let valid_sig = state.schnorr.decrypt_signature(scalar, sig.sig());
+
+    let unsigned = participant
+        .note(sig.note())
+        .ok_or(anyhow!("no {} note for sig", sig.note().as_str()))?;
+    let others = SignedNote::ALL
+        .iter()
+        .filter(|n| **n != sig.note())
+        .filter_map(|n| participant.note(*n))
+        .collect::<Vec<_>>();
+
+    let verification_key: Point<EvenY, Public, NonZero> =
+        Point::from_xonly_bytes(unsigned.pubkey.serialize()).ok_or(anyhow!("invalid pubkey"))?;
+    let verifies = |event: &UnsignedEvent| {
+        let message = Message::<Public>::raw(event.id.as_bytes());
+        state.schnorr.verify(&verification_key, message, &valid_sig)
+    };
+    if !verifies(&unsigned) {
+        if others.iter().any(verifies) {
+            anyhow::bail!("decrypted sig is for another outcome note");
+        }
+        anyhow::bail!("decrypted sig does not verify for {}", unsigned.pubkey);
+    }
 
-            let unsigned = if sig.is_win {
-                bet.win_b()
-            } else {
-                bet.lose_b()
-            };
+    let signature = nostr::secp256k1::schnorr::Signature::from_slice(&valid_sig.to_bytes())?;
+    let signed_event = unsigned.add_signature(signature)?;
+    signed_event
+        .verify()
+        .map_err(|e| anyhow!("signed outcome note does not verify: {e}"))?;
 
-            let signature =
-                nostr::secp256k1::schnorr::Signature::from_slice(&valid_sig.to_bytes())?;
-            let signed_event = unsigned.add_signature(signature)?;
+    Ok(signed_event)
+}
 
-            if sig.is_win {
-                Bet::set_win_outcome_event_id(conn, bet.id, signed_event.id)?;
-            } else {
-                Bet::set_lose_outcome_event_id(conn, bet.id, signed_event.id)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use nostr::{EventBuilder, Keys};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Relays that only take every other event.
+    #[derive(Default)]
+    struct FlakyRelays {
+        tries: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Broadcaster for FlakyRelays {
+        fn name(&self) -> String {
+            "flaky".to_string()
+        }
+
+        async fn broadcast(&self, _event: &Event) -> anyhow::Result<()> {
+            if self.tries.fetch_add(1, Ordering::Relaxed) % 2 == 1 {
+                anyhow::bail!("relay is down");
             }
+            Ok(())
+        }
+    }
+
+    fn note() -> Event {
+        EventBuilder::new(Kind::TextNote, "outcome", Vec::new())
+            .to_event(&Keys::generate())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn failed_notes_dont_stop_publishing() {
+        let relays = FlakyRelays::default();
+        let notes = vec![note(), note(), note()];
 
-            let msg = ClientMessage::event(signed_event.clone());
-            blastr
-                .post("https://nostr.mutinywallet.com")
-                .json(&msg)
-                .send()
-                .await
-                .ok();
-            let event_id = client.send_event(signed_event).await?;
-            info!("Sent event with id: {event_id}")
+        let failed = publish_notes(&relays, &Broadcasters::default(), &notes).await;
+
+        assert_eq!(relays.tries.load(Ordering::Relaxed), 3);
+        assert_eq!(failed, 1);
+    }
+
+    #[test]
+    fn settles_with_the_recorded_notes() {
+        let (win, lose) = (note().id, note().id);
+        let (status, update) = settlement(1, &[(SignedNote::Lose, lose), (SignedNote::Win, win)]);
+
+        assert_eq!(status, BetStatus::Settled);
+        match update {
+            BetUpdate::Settled {
+                win_outcome_event_id,
+                lose_outcome_event_ids,
+                ..
+            } => {
+                assert_eq!(win_outcome_event_id, Some(win));
+                assert_eq!(lose_outcome_event_ids, vec![lose]);
+            }
+            update => panic!("unexpected update {update:?}"),
         }
     }
 
-    Ok(())
+    #[test]
+    fn draw_notes_end_in_a_draw() {
+        let draws = vec![note().id, note().id];
+        let published = draws
+            .iter()
+            .map(|id| (SignedNote::Draw, *id))
+            .collect::<Vec<_>>();
+        let (status, update) = settlement(1, &published);
+
+        assert_eq!(status, BetStatus::Drawn);
+        match update {
+            BetUpdate::Drawn {
+                draw_outcome_event_ids,
+                ..
+            } => assert_eq!(draw_outcome_event_ids, draws),
+            update => panic!("unexpected update {update:?}"),
+        }
+    }
 }
@@ -1,7 +1,9 @@
+use crate::broadcast::{Broadcaster, Broadcasters, HttpBroadcaster, RelayBroadcaster};
 use crate::config::Config;
 use crate::models::bet::Bet;
 use crate::models::MIGRATIONS;
 use crate::routes::*;
+use crate::updates::{Notification, UPDATES_CAPACITY};
 use axum::http::{Method, StatusCode, Uri};
 use axum::routing::{get, post};
 use axum::{http, Extension, Router};
@@ -13,7 +15,7 @@ use dlc::secp256k1_zkp::{All, Secp256k1};
 use log::{error, info};
 use nostr::{EventId, Filter, Keys, Timestamp};
 use nostr_database::NostrDatabase;
-use nostr_sdk::ClientBuilder;
+use nostr_sdk::{Client, ClientBuilder};
 use schnorr_fun::nonce::Deterministic;
 use schnorr_fun::Schnorr;
 use sha2::Sha256;
@@ -22,22 +24,41 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::watch::Sender;
-use tokio::sync::{oneshot, watch, Mutex};
+use tokio::sync::{broadcast, oneshot, watch, Mutex};
 use tokio::time::sleep;
 use tower_http::cors::{Any, CorsLayer};
 
+mod announcement;
+mod auth;
+mod bet_events;
+mod broadcast;
 mod config;
+mod error;
+mod expiry;
+mod keys;
 mod listener;
 mod models;
+mod notifications;
+mod numeric;
 mod routes;
+mod updates;
 mod utils;
+mod validation;
 
 #[derive(Clone)]
 pub struct State {
+    pub config: Config,
+    pub broadcasters: Arc<Broadcasters>,
     pub db_pool: Pool<ConnectionManager<PgConnection>>,
     pub event_channel: Arc<Mutex<Sender<HashSet<EventId>>>>,
+    /// The server's nostr identity
+    pub keys: Keys,
+    /// Client for looking up events on the relays, backed by the events DB
+    pub nostr_client: Client,
     pub schnorr: Schnorr<Sha256, Deterministic<Sha256>>,
     pub secp: Secp256k1<All>,
+    /// Bet updates for streaming subscribers
+    pub updates: broadcast::Sender<Notification>,
 }
 
 #[tokio::main]
@@ -67,11 +88,40 @@ async fn main() -> anyhow::Result<()> {
     let nonce_gen = Deterministic::<Sha256>::default();
     let schnorr = Schnorr::<Sha256, _>::new(nonce_gen);
 
+    let keys = keys::load(&config)?;
+    info!("Server pubkey: {}", keys.public_key());
+
+    let http_client = reqwest::Client::new();
+    let mut broadcasters: Vec<Box<dyn Broadcaster>> = vec![];
+    for url in config.broadcast_urls() {
+        broadcasters.push(Box::new(HttpBroadcaster::new(
+            http_client.clone(),
+            url.clone(),
+        )));
+    }
+    for url in config.publish_relay.iter() {
+        broadcasters.push(Box::new(RelayBroadcaster::new(url.clone(), &keys).await?));
+    }
+
+    let database = nostr_sqlite::SQLiteDatabase::open(&config.events_db).await?;
+
+    let nostr_client = ClientBuilder::new()
+        .signer(&keys)
+        .database(database.clone())
+        .build();
+    nostr_client.add_relays(config.relay.clone()).await?;
+    nostr_client.connect().await;
+
     let state = State {
+        config: config.clone(),
+        broadcasters: Arc::new(Broadcasters::new(broadcasters)),
         db_pool,
         event_channel,
+        keys,
+        nostr_client,
         schnorr,
         secp: Secp256k1::gen_new(),
+        updates: broadcast::channel(UPDATES_CAPACITY).0,
     };
 
     let addr: std::net::SocketAddr = format!("{}:{}", config.bind, config.port)
@@ -82,19 +132,33 @@ async fn main() -> anyhow::Result<()> {
 
     let server_router = Router::new()
         .route("/health-check", get(health_check))
+        .route("/info", get(get_info))
         .route("/create-bet", post(create_bet))
         .route("/add-sigs", post(add_sigs))
+        .route("/accept-bet", post(accept_bet))
+        .route("/open-bets", get(list_open_bets))
         .route("/reject", post(reject))
+        .route("/cancel", post(cancel))
+        .route("/bet-history", get(get_bet_history))
         .route("/list-pending", get(list_pending_events))
         .route("/list-bets", get(list_events))
         .route("/counts", get(get_counts))
         .route("/event-ids", get(get_event_ids))
+        .route("/oracles", get(get_oracles))
+        .route("/updates", get(stream_updates))
+        .route("/settings", post(set_settings))
+        .route("/admin/set-oracle", post(set_oracle))
+        .route("/admin/remove-oracle", post(remove_oracle))
+        .route("/admin/broadcast-stats", get(get_broadcast_stats))
         .fallback(fallback)
         .layer(Extension(state.clone()))
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
-                .allow_headers(vec![http::header::CONTENT_TYPE])
+                .allow_headers(vec![
+                    http::header::CONTENT_TYPE,
+                    http::header::AUTHORIZATION,
+                ])
                 .allow_methods([Method::GET, Method::POST]),
         );
 
@@ -124,7 +188,15 @@ async fn main() -> anyhow::Result<()> {
         let _ = tx.send(());
     });
 
-    let database = nostr_sqlite::SQLiteDatabase::open(config.events_db).await?;
+    tokio::spawn(expiry::start_expiry_task(state.clone()));
+    if !config.disable_dm_notifications {
+        tokio::spawn(notifications::start_dm_task(state.clone()));
+    }
+    if !config.disable_bet_events {
+        tokio::spawn(bet_events::start_bet_events_task(state.clone()));
+    }
+
+    let rebroadcast_keys = state.keys.clone();
 
     let relays = config.relay.clone();
     let listener_db = database.clone();
@@ -153,7 +225,7 @@ async fn main() -> anyhow::Result<()> {
             if let Ok(events) = database.query(vec![filter], Default::default()).await {
                 if !events.is_empty() {
                     let client = ClientBuilder::new()
-                        .signer(Keys::generate())
+                        .signer(&rebroadcast_keys)
                         .database(database.clone())
                         .build();
                     client.add_relays(relays.clone()).await.unwrap();
@@ -0,0 +1,79 @@
+use super::schema::oracle_attestations;
+use diesel::prelude::*;
+use dlc_messages::oracle_msgs::OracleAttestation;
+use lightning::util::ser::{Readable, Writeable};
+use nostr::EventId;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    AsChangeset,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+)]
+#[diesel(primary_key(id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Attestation {
+    pub id: i32,
+    oracle_event_id: Vec<u8>,
+    attestation: Vec<u8>,
+    created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = oracle_attestations)]
+struct NewAttestation {
+    oracle_event_id: Vec<u8>,
+    attestation: Vec<u8>,
+}
+
+impl Attestation {
+    pub fn oracle_event_id(&self) -> EventId {
+        EventId::from_slice(&self.oracle_event_id).expect("invalid oracle_event_id")
+    }
+
+    pub fn attestation(&self) -> OracleAttestation {
+        let mut cursor = Cursor::new(&self.attestation);
+        OracleAttestation::read(&mut cursor).expect("invalid oracle attestation")
+    }
+
+    /// Stores the attestation, keeping the first one seen for an oracle event.
+    pub fn create(
+        conn: &mut PgConnection,
+        oracle_event_id: EventId,
+        attestation: &OracleAttestation,
+    ) -> anyhow::Result<()> {
+        let new = NewAttestation {
+            oracle_event_id: oracle_event_id.to_bytes().to_vec(),
+            attestation: attestation.encode(),
+        };
+        diesel::insert_into(oracle_attestations::table)
+            .values(new)
+            .on_conflict(oracle_attestations::oracle_event_id)
+            .do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn get_by_oracle_events(
+        conn: &mut PgConnection,
+        oracle_event_ids: &[EventId],
+    ) -> anyhow::Result<Vec<Self>> {
+        let bytes = oracle_event_ids
+            .iter()
+            .map(|id| id.to_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let res = oracle_attestations::table
+            .filter(oracle_attestations::oracle_event_id.eq_any(bytes))
+            .load(conn)?;
+
+        Ok(res)
+    }
+}
@@ -1,13 +1,16 @@
-use super::schema::bets;
+use super::bet_history::BetHistory;
+use super::schema::{bet_history, bet_oracles, bet_participants, bets};
+use crate::error::ApiError;
 use diesel::prelude::*;
 use dlc_messages::oracle_msgs::OracleAnnouncement;
 use lightning::util::ser::{Readable, Writeable};
 use nostr::key::XOnlyPublicKey;
-use nostr::{EventId, JsonUtil, UnsignedEvent};
+use nostr::{Event, EventId, Kind, Tag, UnsignedEvent};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashSet;
 use std::io::Cursor;
+use std::str::FromStr;
 
 #[derive(
     Queryable,
@@ -26,100 +29,281 @@ use std::io::Cursor;
 pub struct Bet {
     pub id: i32,
     oracle_announcement: Vec<u8>,
-    user_a: Vec<u8>,
-    win_a: Value,
-    lose_a: Value,
-    user_b: Vec<u8>,
-    win_b: Value,
-    lose_b: Value,
     oracle_event_id: Vec<u8>,
-    pub needs_reply: bool,
-    win_outcome_event_id: Option<Vec<u8>>,
-    lose_outcome_event_id: Option<Vec<u8>>,
     created_at: chrono::NaiveDateTime,
+    pub threshold: i32,
+    pub expires_at: chrono::NaiveDateTime,
+    pub expired_at: Option<chrono::NaiveDateTime>,
+    pub voided_at: Option<chrono::NaiveDateTime>,
+    pub settlement_error: Option<String>,
+    status: String,
+    rejected_by: Option<Vec<u8>>,
+    pub rejected_at: Option<chrono::NaiveDateTime>,
+    reject_event: Option<Value>,
+    counterparty_win_template: Option<Value>,
+    counterparty_lose_template: Option<Value>,
+    /// How many people take part, including the proposer
+    pub participant_count: i32,
+    counterparty_draw_template: Option<Value>,
+    /// Outcomes nobody wins on, the draw notes are published if there are any
+    pub draw_outcomes: Vec<String>,
+    /// Outcomes nobody signed for, nothing is published if they happen
+    pub no_contest_outcomes: Vec<String>,
 }
 
-#[derive(Insertable, AsChangeset)]
-#[diesel(table_name = bets)]
-struct NewBet {
-    oracle_announcement: Vec<u8>,
-    user_a: Vec<u8>,
-    win_a: Value,
-    lose_a: Value,
-    user_b: Vec<u8>,
-    win_b: Value,
-    lose_b: Value,
-    oracle_event_id: Vec<u8>,
+/// A participant's outcome notes, one of which gets published when the bet
+/// settles.
+#[derive(Debug, Clone)]
+pub struct OutcomeNotes {
+    pub win: UnsignedEvent,
+    pub lose: UnsignedEvent,
+    /// Draw notes are optional, either everyone has one or nobody does
+    pub draw: Option<UnsignedEvent>,
 }
 
-impl Bet {
-    pub fn oracle_announcement(&self) -> OracleAnnouncement {
-        let mut cursor = Cursor::new(&self.oracle_announcement);
-        OracleAnnouncement::read(&mut cursor).expect("invalid oracle announcement")
+/// The kind, content and tags a counterparty's outcome note must have to
+/// accept an open bet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NoteTemplate {
+    pub kind: Kind,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+impl NoteTemplate {
+    pub fn matches(&self, event: &UnsignedEvent) -> bool {
+        event.kind == self.kind && event.content == self.content && event.tags == self.tags
     }
+}
 
-    pub fn user_a(&self) -> XOnlyPublicKey {
-        XOnlyPublicKey::from_slice(&self.user_a).expect("invalid user_a")
+/// Templates for each of the notes of whoever accepts an open bet.
+#[derive(Debug, Clone)]
+pub struct NoteTemplates {
+    pub win: NoteTemplate,
+    pub lose: NoteTemplate,
+    pub draw: Option<NoteTemplate>,
+}
+
+/// Who the proposer is betting against.
+pub enum Counterparties {
+    /// The named counterparties and their outcome notes
+    Named(Vec<OutcomeNotes>),
+    /// Whoever first accepts with notes matching the templates, until the
+    /// bet has `participants` people in it
+    Open {
+        templates: NoteTemplates,
+        participants: usize,
+    },
+}
+
+impl Counterparties {
+    /// How many people take part in the bet, including the proposer.
+    pub fn participant_count(&self) -> usize {
+        match self {
+            Counterparties::Named(named) => named.len() + 1,
+            Counterparties::Open { participants, .. } => *participants,
+        }
     }
+}
+
+/// Where a bet is in its lifecycle.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BetStatus {
+    /// Waiting for the counterparties to add their sigs
+    Proposed,
+    /// Everyone signed, waiting for the oracle
+    Active,
+    /// Some oracles attested, waiting for enough of them to agree
+    Attested,
+    /// Enough oracles agreed, the outcome notes are being published
+    Settling,
+    /// The outcome notes were published
+    Settled,
+    /// The oracles attested to a draw, the draw notes were published if
+    /// there were any
+    Drawn,
+    /// The oracles attested to an outcome no participant signed for
+    NoContest,
+    /// A participant turned the bet down
+    Rejected,
+    /// The proposer withdrew the bet before it was accepted
+    Cancelled,
+    /// Not every counterparty answered before the deadline
+    Expired,
+    /// The oracle never attested, the outcome notes will never be published
+    Voided,
+    /// The outcome notes could not be signed or recorded, see the settlement
+    /// error
+    Failed,
+}
 
-    pub fn win_a(&self) -> UnsignedEvent {
-        UnsignedEvent::from_json(self.win_a.to_string()).expect("invalid win_a")
+impl BetStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BetStatus::Proposed => "proposed",
+            BetStatus::Active => "active",
+            BetStatus::Attested => "attested",
+            BetStatus::Settling => "settling",
+            BetStatus::Settled => "settled",
+            BetStatus::Drawn => "drawn",
+            BetStatus::NoContest => "no_contest",
+            BetStatus::Rejected => "rejected",
+            BetStatus::Cancelled => "cancelled",
+            BetStatus::Expired => "expired",
+            BetStatus::Voided => "voided",
+            BetStatus::Failed => "failed",
+        }
     }
 
-    pub fn lose_a(&self) -> UnsignedEvent {
-        UnsignedEvent::from_json(self.lose_a.to_string()).expect("invalid lose_a")
+    /// The statuses a bet can move to this one from. Moving to the status a
+    /// bet already has changes nothing, except for settling which only one
+    /// settlement can start.
+    pub fn allowed_from(&self) -> &'static [BetStatus] {
+        match self {
+            BetStatus::Proposed => &[],
+            BetStatus::Active => &[BetStatus::Proposed],
+            BetStatus::Attested => &[BetStatus::Active],
+            BetStatus::Settling => &[BetStatus::Active, BetStatus::Attested],
+            BetStatus::Settled => &[BetStatus::Settling],
+            BetStatus::Drawn => &[BetStatus::Active, BetStatus::Attested, BetStatus::Settling],
+            BetStatus::NoContest => &[BetStatus::Active, BetStatus::Attested],
+            BetStatus::Rejected => &[BetStatus::Proposed],
+            BetStatus::Cancelled => &[BetStatus::Proposed],
+            BetStatus::Expired => &[BetStatus::Proposed],
+            BetStatus::Voided => &[BetStatus::Active, BetStatus::Attested],
+            BetStatus::Failed => &[BetStatus::Settling],
+        }
     }
 
-    pub fn user_b(&self) -> XOnlyPublicKey {
-        XOnlyPublicKey::from_slice(&self.user_b).expect("invalid user_a")
+    /// Bets that are waiting on the oracles, or on publishing their notes.
+    pub const UNFINISHED: [BetStatus; 3] =
+        [BetStatus::Active, BetStatus::Attested, BetStatus::Settling];
+
+    /// Bets that settled one way or another.
+    pub const COMPLETED: [BetStatus; 3] =
+        [BetStatus::Settled, BetStatus::Drawn, BetStatus::NoContest];
+}
+
+impl FromStr for BetStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "proposed" => Ok(BetStatus::Proposed),
+            "active" => Ok(BetStatus::Active),
+            "attested" => Ok(BetStatus::Attested),
+            "settling" => Ok(BetStatus::Settling),
+            "settled" => Ok(BetStatus::Settled),
+            "drawn" => Ok(BetStatus::Drawn),
+            "no_contest" => Ok(BetStatus::NoContest),
+            "rejected" => Ok(BetStatus::Rejected),
+            "cancelled" => Ok(BetStatus::Cancelled),
+            "expired" => Ok(BetStatus::Expired),
+            "voided" => Ok(BetStatus::Voided),
+            "failed" => Ok(BetStatus::Failed),
+            _ => Err(anyhow::anyhow!("invalid bet status: {s}")),
+        }
     }
+}
+
+fn status_strs(statuses: &[BetStatus]) -> Vec<&'static str> {
+    statuses.iter().map(BetStatus::as_str).collect()
+}
 
-    pub fn win_b(&self) -> UnsignedEvent {
-        UnsignedEvent::from_json(self.win_b.to_string()).expect("invalid win_b")
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = bets)]
+struct NewBet {
+    oracle_announcement: Vec<u8>,
+    oracle_event_id: Vec<u8>,
+    threshold: i32,
+    expires_at: chrono::NaiveDateTime,
+    counterparty_win_template: Option<Value>,
+    counterparty_lose_template: Option<Value>,
+    participant_count: i32,
+    counterparty_draw_template: Option<Value>,
+    draw_outcomes: Vec<String>,
+    no_contest_outcomes: Vec<String>,
+}
+
+impl Bet {
+    pub fn oracle_announcement(&self) -> OracleAnnouncement {
+        let mut cursor = Cursor::new(&self.oracle_announcement);
+        OracleAnnouncement::read(&mut cursor).expect("invalid oracle announcement")
     }
 
-    pub fn lose_b(&self) -> UnsignedEvent {
-        UnsignedEvent::from_json(self.lose_b.to_string()).expect("invalid lose_b")
+    /// Templates for the counterparties' notes, if the bet was proposed open.
+    pub fn counterparty_templates(&self) -> Option<NoteTemplates> {
+        let parse = |v: &Value| serde_json::from_value(v.clone()).expect("invalid template");
+        self.counterparty_win_template
+            .as_ref()
+            .zip(self.counterparty_lose_template.as_ref())
+            .map(|(win, lose)| NoteTemplates {
+                win: parse(win),
+                lose: parse(lose),
+                draw: self.counterparty_draw_template.as_ref().map(parse),
+            })
     }
 
     pub fn oracle_event_id(&self) -> EventId {
         EventId::from_slice(&self.oracle_event_id).expect("invalid oracle_event_id")
     }
 
-    pub fn win_outcome_event_id(&self) -> Option<EventId> {
-        self.win_outcome_event_id
-            .as_ref()
-            .map(|b| EventId::from_slice(b).expect("invalid win_outcome_event_id"))
+    pub fn created_at(&self) -> chrono::NaiveDateTime {
+        self.created_at
     }
 
-    pub fn lose_outcome_event_id(&self) -> Option<EventId> {
-        self.lose_outcome_event_id
-            .as_ref()
-            .map(|b| EventId::from_slice(b).expect("invalid lose_outcome_event_id"))
+    pub fn status(&self) -> BetStatus {
+        BetStatus::from_str(&self.status).expect("invalid status")
     }
 
+    /// When the proposal lapses, either at its deadline or once the oracle
+    /// event has matured, whichever comes first.
+    pub fn proposal_deadline(&self) -> chrono::NaiveDateTime {
+        let maturity = self.oracle_announcement().oracle_event.event_maturity_epoch;
+        chrono::NaiveDateTime::from_timestamp_opt(maturity as i64, 0)
+            .map_or(self.expires_at, |maturity| maturity.min(self.expires_at))
+    }
+
+    /// Creates the bet, its participants are added separately.
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
         conn: &mut PgConnection,
         oracle_announcement: OracleAnnouncement,
-        win_a: UnsignedEvent,
-        lose_a: UnsignedEvent,
-        win_b: UnsignedEvent,
-        lose_b: UnsignedEvent,
         oracle_event_id: EventId,
+        threshold: i32,
+        expires_at: chrono::NaiveDateTime,
+        participant_count: i32,
+        templates: Option<NoteTemplates>,
+        draw_outcomes: Vec<String>,
+        no_contest_outcomes: Vec<String>,
+        proposer: XOnlyPublicKey,
     ) -> anyhow::Result<Self> {
+        let (win_template, lose_template, draw_template) = match templates {
+            Some(templates) => (
+                Some(serde_json::to_value(templates.win)?),
+                Some(serde_json::to_value(templates.lose)?),
+                templates.draw.map(serde_json::to_value).transpose()?,
+            ),
+            None => (None, None, None),
+        };
         let new_bet = NewBet {
             oracle_announcement: oracle_announcement.encode(),
-            user_a: win_a.pubkey.serialize().to_vec(),
-            win_a: serde_json::to_value(win_a)?,
-            lose_a: serde_json::to_value(lose_a)?,
-            user_b: win_b.pubkey.serialize().to_vec(),
-            win_b: serde_json::to_value(win_b)?,
-            lose_b: serde_json::to_value(lose_b)?,
             oracle_event_id: oracle_event_id.to_bytes().to_vec(),
+            threshold,
+            expires_at,
+            counterparty_win_template: win_template,
+            counterparty_lose_template: lose_template,
+            participant_count,
+            counterparty_draw_template: draw_template,
+            draw_outcomes,
+            no_contest_outcomes,
         };
         let res = diesel::insert_into(bets::table)
             .values(new_bet)
             .get_result::<Self>(conn)?;
+        BetHistory::create(conn, res.id, None, res.status(), Some(proposer), None)?;
         Ok(res)
     }
 
@@ -128,50 +312,89 @@ impl Bet {
         Ok(res)
     }
 
+    /// Locks the bet until the end of the transaction.
+    pub fn lock(conn: &mut PgConnection, id: i32) -> anyhow::Result<Self> {
+        let res = bets::table
+            .find(id)
+            .for_update()
+            .first::<Self>(conn)
+            .optional()?
+            .ok_or(ApiError::NotFound("bet not found".to_string()))?;
+        Ok(res)
+    }
+
+    /// Bets on the oracle event that are still waiting on it.
     pub fn get_by_oracle_event(
         conn: &mut PgConnection,
         oracle_event_id: &EventId,
     ) -> anyhow::Result<Vec<Self>> {
         let bytes = oracle_event_id.to_bytes().to_vec();
         let res = bets::table
-            .filter(bets::oracle_event_id.eq(bytes))
-            .filter(
-                bets::win_outcome_event_id
-                    .is_null()
-                    .or(bets::lose_outcome_event_id.is_null()),
-            )
+            .inner_join(bet_oracles::table)
+            .filter(bet_oracles::oracle_event_id.eq(bytes))
+            .filter(bets::status.eq_any(status_strs(&BetStatus::UNFINISHED)))
+            .select(bets::all_columns)
             .load::<Self>(conn)?;
         Ok(res)
     }
 
+    /// Proposals the user hasn't answered yet whose deadline hasn't passed.
+    /// Those the oracle event matured on are left for the caller to check.
     pub fn get_pending_bets(
         conn: &mut PgConnection,
         user: XOnlyPublicKey,
+        now: chrono::NaiveDateTime,
     ) -> anyhow::Result<Vec<Bet>> {
         let res = bets::table
-            .filter(bets::needs_reply.eq(true))
-            .filter(bets::user_b.eq(user.serialize().to_vec()))
+            .inner_join(bet_participants::table)
+            .filter(bets::status.eq(BetStatus::Proposed.as_str()))
+            .filter(bets::expires_at.gt(now))
+            .filter(bet_participants::pubkey.eq(user.serialize().to_vec()))
+            .filter(bet_participants::accepted_at.is_null())
+            .select(bets::all_columns)
+            .load::<Self>(conn)?;
+        Ok(res)
+    }
+
+    /// Open bets that haven't started yet, regardless of their deadline.
+    /// Some of them may already be full.
+    pub fn get_open_bets(conn: &mut PgConnection) -> anyhow::Result<Vec<Bet>> {
+        let res = bets::table
+            .filter(bets::status.eq(BetStatus::Proposed.as_str()))
+            .filter(bets::counterparty_win_template.is_not_null())
+            .order(bets::created_at.desc())
             .load::<Self>(conn)?;
         Ok(res)
     }
 
+    /// Proposals that are still open, regardless of their deadline.
+    pub fn get_open_proposals(conn: &mut PgConnection) -> anyhow::Result<Vec<Bet>> {
+        let res = bets::table
+            .filter(bets::status.eq(BetStatus::Proposed.as_str()))
+            .load::<Self>(conn)?;
+        Ok(res)
+    }
+
+    /// Every bet of the user's that is no longer an open proposal.
     pub fn get_active_bets(
         conn: &mut PgConnection,
         user: XOnlyPublicKey,
     ) -> anyhow::Result<Vec<Bet>> {
-        let bytes = user.serialize().to_vec();
         let res = bets::table
-            .filter(bets::needs_reply.eq(false))
-            .filter(bets::user_b.eq(&bytes).or(bets::user_a.eq(&bytes)))
+            .inner_join(bet_participants::table)
+            .filter(bets::status.ne(BetStatus::Proposed.as_str()))
+            .filter(bet_participants::pubkey.eq(user.serialize().to_vec()))
+            .select(bets::all_columns)
             .load::<Self>(conn)?;
         Ok(res)
     }
 
+    /// The oracle events of every bet that is still waiting on its oracles.
     pub fn get_unfinished_bets(conn: &mut PgConnection) -> anyhow::Result<HashSet<EventId>> {
         let res = bets::table
-            .filter(bets::needs_reply.eq(false))
-            .filter(bets::win_outcome_event_id.is_null())
-            .select(bets::oracle_event_id)
+            .inner_join(bet_oracles::table)
+            .filter(bets::status.eq_any(status_strs(&BetStatus::UNFINISHED)))
+            .select(bet_oracles::oracle_event_id)
             .load::<Vec<u8>>(conn)?
             .into_iter()
             .map(|b| EventId::from_slice(&b).expect("invalid oracle_event_id"))
@@ -179,39 +402,137 @@ impl Bet {
         Ok(res)
     }
 
-    pub fn set_needs_reply(conn: &mut PgConnection, id: i32) -> anyhow::Result<Self> {
-        let res = diesel::update(bets::table.find(id))
-            .set(bets::needs_reply.eq(false))
-            .get_result::<Self>(conn)?;
+    /// Active bets the oracles have not settled yet.
+    pub fn get_unsettled_bets(conn: &mut PgConnection) -> anyhow::Result<Vec<Bet>> {
+        let res = bets::table
+            .filter(bets::status.eq_any(status_strs(&[BetStatus::Active, BetStatus::Attested])))
+            .load::<Self>(conn)?;
         Ok(res)
     }
 
-    pub fn set_win_outcome_event_id(
+    /// Bets that started settling before the cutoff and are still at it.
+    pub fn get_stale_settling_bets(
+        conn: &mut PgConnection,
+        cutoff: chrono::NaiveDateTime,
+    ) -> anyhow::Result<Vec<Bet>> {
+        let res = bets::table
+            .inner_join(bet_history::table)
+            .filter(bets::status.eq(BetStatus::Settling.as_str()))
+            .filter(bet_history::to_status.eq(BetStatus::Settling.as_str()))
+            .filter(bet_history::created_at.le(cutoff))
+            .select(bets::all_columns)
+            .load::<Self>(conn)?;
+        Ok(res)
+    }
+
+    /// Moves the bet to the new status and records it in the bet's history,
+    /// failing if that isn't a valid transition from the status it is in.
+    pub fn transition(
+        conn: &mut PgConnection,
+        id: i32,
+        to: BetStatus,
+        actor: Option<XOnlyPublicKey>,
+        event: Option<&Event>,
+    ) -> anyhow::Result<Self> {
+        conn.transaction(|conn| {
+            let bet = Self::lock(conn, id)?;
+
+            let from = bet.status();
+            if from == to && to != BetStatus::Settling {
+                return Ok(bet);
+            }
+            if !to.allowed_from().contains(&from) {
+                anyhow::bail!(ApiError::Conflict(format!(
+                    "bet is {}, it can't become {}",
+                    from.as_str(),
+                    to.as_str()
+                )));
+            }
+
+            let bet = diesel::update(bets::table.find(id))
+                .set(bets::status.eq(to.as_str()))
+                .get_result::<Self>(conn)?;
+            BetHistory::create(conn, id, Some(from), to, actor, event)?;
+            Ok(bet)
+        })
+    }
+
+    pub fn set_status(conn: &mut PgConnection, id: i32, status: BetStatus) -> anyhow::Result<Self> {
+        Self::transition(conn, id, status, None, None)
+    }
+
+    pub fn set_voided(
         conn: &mut PgConnection,
         id: i32,
-        win_outcome_event_id: EventId,
-    ) -> anyhow::Result<()> {
-        diesel::update(bets::table.find(id))
-            .set(bets::win_outcome_event_id.eq(win_outcome_event_id.to_bytes().to_vec()))
-            .execute(conn)?;
-        Ok(())
+        voided_at: chrono::NaiveDateTime,
+    ) -> anyhow::Result<Self> {
+        conn.transaction(|conn| {
+            Self::set_status(conn, id, BetStatus::Voided)?;
+            let res = diesel::update(bets::table.find(id))
+                .set(bets::voided_at.eq(voided_at))
+                .get_result::<Self>(conn)?;
+            Ok(res)
+        })
     }
 
-    pub fn set_lose_outcome_event_id(
+    pub fn set_expired(
         conn: &mut PgConnection,
         id: i32,
-        lose_outcome_event_id: EventId,
-    ) -> anyhow::Result<()> {
-        diesel::update(bets::table.find(id))
-            .set(bets::lose_outcome_event_id.eq(lose_outcome_event_id.to_bytes().to_vec()))
-            .execute(conn)?;
-        Ok(())
+        expired_at: chrono::NaiveDateTime,
+    ) -> anyhow::Result<Self> {
+        conn.transaction(|conn| {
+            Self::set_status(conn, id, BetStatus::Expired)?;
+            let res = diesel::update(bets::table.find(id))
+                .set(bets::expired_at.eq(expired_at))
+                .get_result::<Self>(conn)?;
+            Ok(res)
+        })
+    }
+
+    pub fn set_settlement_error(
+        conn: &mut PgConnection,
+        id: i32,
+        settlement_error: &str,
+    ) -> anyhow::Result<Self> {
+        conn.transaction(|conn| {
+            Self::set_status(conn, id, BetStatus::Failed)?;
+            let res = diesel::update(bets::table.find(id))
+                .set(bets::settlement_error.eq(settlement_error))
+                .get_result::<Self>(conn)?;
+            Ok(res)
+        })
+    }
+
+    /// Marks the bet as rejected, keeping who rejected it and the signed
+    /// event they did it with.
+    pub fn set_rejected(
+        conn: &mut PgConnection,
+        id: i32,
+        rejected_by: XOnlyPublicKey,
+        event: &Event,
+    ) -> anyhow::Result<Self> {
+        conn.transaction(|conn| {
+            Self::transition(
+                conn,
+                id,
+                BetStatus::Rejected,
+                Some(rejected_by),
+                Some(event),
+            )?;
+            let res = diesel::update(bets::table.find(id))
+                .set((
+                    bets::rejected_by.eq(rejected_by.serialize().to_vec()),
+                    bets::rejected_at.eq(chrono::Utc::now().naive_utc()),
+                    bets::reject_event.eq(serde_json::to_value(event)?),
+                ))
+                .get_result::<Self>(conn)?;
+            Ok(res)
+        })
     }
 
     pub fn get_active_event_count(conn: &mut PgConnection) -> anyhow::Result<i64> {
         let res = bets::table
-            .filter(bets::needs_reply.eq(false))
-            .filter(bets::win_outcome_event_id.is_null())
+            .filter(bets::status.eq_any(status_strs(&BetStatus::UNFINISHED)))
             .count()
             .get_result::<i64>(conn)?;
 
@@ -220,34 +541,92 @@ impl Bet {
 
     pub fn get_completed_event_count(conn: &mut PgConnection) -> anyhow::Result<i64> {
         let res = bets::table
-            .filter(bets::win_outcome_event_id.is_not_null())
+            .filter(bets::status.eq_any(status_strs(&BetStatus::COMPLETED)))
             .count()
             .get_result::<i64>(conn)?;
 
         Ok(res)
     }
+}
 
-    pub fn get_event_ids(conn: &mut PgConnection) -> anyhow::Result<Vec<EventId>> {
-        let events = bets::table
-            .select((bets::win_outcome_event_id, bets::lose_outcome_event_id))
-            .load::<(Option<Vec<u8>>, Option<Vec<u8>>)>(conn)?
-            .into_iter()
-            .flat_map(|(w, l)| {
-                let w = w.map(|w| vec![EventId::from_slice(&w).expect("event_id")]);
-                let l = l.map(|l| vec![EventId::from_slice(&l).expect("event_id")]);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                let mut vec = w.unwrap_or_default();
-                vec.extend(l.unwrap_or_default());
+    const ALL: [BetStatus; 12] = [
+        BetStatus::Proposed,
+        BetStatus::Active,
+        BetStatus::Attested,
+        BetStatus::Settling,
+        BetStatus::Settled,
+        BetStatus::Drawn,
+        BetStatus::NoContest,
+        BetStatus::Rejected,
+        BetStatus::Cancelled,
+        BetStatus::Expired,
+        BetStatus::Voided,
+        BetStatus::Failed,
+    ];
 
-                vec
-            })
-            .collect();
+    /// The statuses a bet can move to from `from`.
+    fn next(from: BetStatus) -> Vec<BetStatus> {
+        ALL.into_iter()
+            .filter(|to| to.allowed_from().contains(&from))
+            .collect()
+    }
+
+    #[test]
+    fn status_round_trip() {
+        for status in ALL {
+            assert_eq!(BetStatus::from_str(status.as_str()).unwrap(), status);
+        }
+        assert!(BetStatus::from_str("unknown").is_err());
+    }
+
+    #[test]
+    fn proposals_are_answered_once() {
+        assert_eq!(
+            next(BetStatus::Proposed),
+            vec![
+                BetStatus::Active,
+                BetStatus::Rejected,
+                BetStatus::Cancelled,
+                BetStatus::Expired,
+            ]
+        );
+    }
+
+    #[test]
+    fn settling_ends_in_a_result_or_failure() {
+        assert_eq!(
+            next(BetStatus::Settling),
+            vec![BetStatus::Settled, BetStatus::Drawn, BetStatus::Failed]
+        );
+        assert!(!BetStatus::Settling
+            .allowed_from()
+            .contains(&BetStatus::Settling));
+    }
 
-        Ok(events)
+    #[test]
+    fn finished_bets_stay_finished() {
+        for status in BetStatus::COMPLETED {
+            assert_eq!(next(status), vec![], "{}", status.as_str());
+        }
+        for status in [
+            BetStatus::Rejected,
+            BetStatus::Cancelled,
+            BetStatus::Expired,
+            BetStatus::Voided,
+            BetStatus::Failed,
+        ] {
+            assert_eq!(next(status), vec![], "{}", status.as_str());
+        }
     }
 
-    pub fn delete_by_bet_id(conn: &mut PgConnection, bet_id: i32) -> anyhow::Result<()> {
-        diesel::delete(bets::table.find(bet_id)).execute(conn)?;
-        Ok(())
+    #[test]
+    fn unfinished_bets_can_be_voided() {
+        for status in [BetStatus::Active, BetStatus::Attested] {
+            assert!(next(status).contains(&BetStatus::Voided));
+        }
     }
 }
@@ -0,0 +1,105 @@
+use super::bet::{Bet, BetStatus};
+use super::schema::bet_history;
+use diesel::prelude::*;
+use nostr::key::XOnlyPublicKey;
+use nostr::{Event, JsonUtil};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// A change in a bet's status, along with who made it and the signed event
+/// they made it with, if any.
+#[derive(
+    Associations,
+    Queryable,
+    Insertable,
+    Identifiable,
+    AsChangeset,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = bet_history)]
+#[diesel(belongs_to(Bet, foreign_key = bet_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct BetHistory {
+    pub id: i32,
+    pub bet_id: i32,
+    from_status: Option<String>,
+    to_status: String,
+    actor: Option<Vec<u8>>,
+    event: Option<Value>,
+    created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = bet_history)]
+struct NewBetHistory {
+    bet_id: i32,
+    from_status: Option<String>,
+    to_status: String,
+    actor: Option<Vec<u8>>,
+    event: Option<Value>,
+}
+
+impl BetHistory {
+    pub fn from_status(&self) -> Option<BetStatus> {
+        self.from_status
+            .as_ref()
+            .map(|s| BetStatus::from_str(s).expect("invalid status"))
+    }
+
+    pub fn to_status(&self) -> BetStatus {
+        BetStatus::from_str(&self.to_status).expect("invalid status")
+    }
+
+    pub fn actor(&self) -> Option<XOnlyPublicKey> {
+        self.actor
+            .as_ref()
+            .map(|b| XOnlyPublicKey::from_slice(b).expect("invalid actor"))
+    }
+
+    pub fn event(&self) -> Option<Event> {
+        self.event
+            .as_ref()
+            .map(|v| Event::from_json(v.to_string()).expect("invalid event"))
+    }
+
+    pub fn created_at(&self) -> chrono::NaiveDateTime {
+        self.created_at
+    }
+
+    pub fn create(
+        conn: &mut PgConnection,
+        bet_id: i32,
+        from_status: Option<BetStatus>,
+        to_status: BetStatus,
+        actor: Option<XOnlyPublicKey>,
+        event: Option<&Event>,
+    ) -> anyhow::Result<()> {
+        let new = NewBetHistory {
+            bet_id,
+            from_status: from_status.map(|s| s.as_str().to_string()),
+            to_status: to_status.as_str().to_string(),
+            actor: actor.map(|a| a.serialize().to_vec()),
+            event: event.map(serde_json::to_value).transpose()?,
+        };
+        diesel::insert_into(bet_history::table)
+            .values(new)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn get_by_bet_id(conn: &mut PgConnection, bet_id: i32) -> anyhow::Result<Vec<Self>> {
+        let res = bet_history::table
+            .filter(bet_history::bet_id.eq(bet_id))
+            .order(bet_history::id.asc())
+            .load(conn)?;
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,99 @@
+use super::bet::Bet;
+use super::schema::bet_oracles;
+use diesel::prelude::*;
+use dlc_messages::oracle_msgs::OracleAnnouncement;
+use lightning::util::ser::{Readable, Writeable};
+use nostr::EventId;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+#[derive(
+    Associations,
+    Queryable,
+    Insertable,
+    Identifiable,
+    AsChangeset,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+)]
+#[diesel(primary_key(id))]
+#[diesel(belongs_to(Bet, foreign_key = bet_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct BetOracle {
+    pub id: i32,
+    pub bet_id: i32,
+    pub oracle_index: i32,
+    oracle_announcement: Vec<u8>,
+    oracle_event_id: Vec<u8>,
+    pub announcement_verified: bool,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = bet_oracles)]
+struct NewBetOracle {
+    bet_id: i32,
+    oracle_index: i32,
+    oracle_announcement: Vec<u8>,
+    oracle_event_id: Vec<u8>,
+    announcement_verified: bool,
+}
+
+impl BetOracle {
+    pub fn oracle_announcement(&self) -> OracleAnnouncement {
+        let mut cursor = Cursor::new(&self.oracle_announcement);
+        OracleAnnouncement::read(&mut cursor).expect("invalid oracle announcement")
+    }
+
+    pub fn oracle_event_id(&self) -> EventId {
+        EventId::from_slice(&self.oracle_event_id).expect("invalid oracle_event_id")
+    }
+
+    pub fn create_all(
+        conn: &mut PgConnection,
+        bet_id: i32,
+        oracles: Vec<(OracleAnnouncement, EventId, bool)>,
+    ) -> anyhow::Result<()> {
+        let new_oracles = oracles
+            .into_iter()
+            .enumerate()
+            .map(
+                |(index, (announcement, event_id, announcement_verified))| NewBetOracle {
+                    bet_id,
+                    oracle_index: index as i32,
+                    oracle_announcement: announcement.encode(),
+                    oracle_event_id: event_id.to_bytes().to_vec(),
+                    announcement_verified,
+                },
+            )
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(bet_oracles::table)
+            .values(new_oracles)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn get_by_bet_id(conn: &mut PgConnection, bet_id: i32) -> anyhow::Result<Vec<Self>> {
+        let res = bet_oracles::table
+            .filter(bet_oracles::bet_id.eq(bet_id))
+            .order(bet_oracles::oracle_index.asc())
+            .load(conn)?;
+
+        Ok(res)
+    }
+
+    pub fn get_by_oracle_event(
+        conn: &mut PgConnection,
+        oracle_event_id: &EventId,
+    ) -> anyhow::Result<Vec<Self>> {
+        let res = bet_oracles::table
+            .filter(bet_oracles::oracle_event_id.eq(oracle_event_id.to_bytes().to_vec()))
+            .load(conn)?;
+
+        Ok(res)
+    }
+}
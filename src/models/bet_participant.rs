@@ -0,0 +1,165 @@
+use super::bet::{Bet, OutcomeNotes};
+use super::schema::bet_participants;
+use super::sig::SignedNote;
+use diesel::prelude::*;
+use nostr::key::XOnlyPublicKey;
+use nostr::{EventId, JsonUtil, UnsignedEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(
+    Associations,
+    Queryable,
+    Insertable,
+    Identifiable,
+    AsChangeset,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+)]
+#[diesel(primary_key(id))]
+#[diesel(belongs_to(Bet, foreign_key = bet_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct BetParticipant {
+    pub id: i32,
+    pub bet_id: i32,
+    pub participant_index: i32,
+    pubkey: Vec<u8>,
+    win_event: Value,
+    lose_event: Value,
+    /// When they added their sigs, the proposer's come with the bet
+    pub accepted_at: Option<chrono::NaiveDateTime>,
+    outcome_event_id: Option<Vec<u8>>,
+    created_at: chrono::NaiveDateTime,
+    draw_event: Option<Value>,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = bet_participants)]
+struct NewBetParticipant {
+    bet_id: i32,
+    participant_index: i32,
+    pubkey: Vec<u8>,
+    win_event: Value,
+    lose_event: Value,
+    accepted_at: Option<chrono::NaiveDateTime>,
+    draw_event: Option<Value>,
+}
+
+impl BetParticipant {
+    pub fn pubkey(&self) -> XOnlyPublicKey {
+        XOnlyPublicKey::from_slice(&self.pubkey).expect("invalid pubkey")
+    }
+
+    pub fn win_event(&self) -> UnsignedEvent {
+        UnsignedEvent::from_json(self.win_event.to_string()).expect("invalid win_event")
+    }
+
+    pub fn lose_event(&self) -> UnsignedEvent {
+        UnsignedEvent::from_json(self.lose_event.to_string()).expect("invalid lose_event")
+    }
+
+    /// Only bets with draw outcomes have draw notes
+    pub fn draw_event(&self) -> Option<UnsignedEvent> {
+        self.draw_event
+            .as_ref()
+            .map(|v| UnsignedEvent::from_json(v.to_string()).expect("invalid draw_event"))
+    }
+
+    pub fn notes(&self) -> OutcomeNotes {
+        OutcomeNotes {
+            win: self.win_event(),
+            lose: self.lose_event(),
+            draw: self.draw_event(),
+        }
+    }
+
+    /// The note a sig of theirs is for.
+    pub fn note(&self, note: SignedNote) -> Option<UnsignedEvent> {
+        match note {
+            SignedNote::Win => Some(self.win_event()),
+            SignedNote::Lose => Some(self.lose_event()),
+            SignedNote::Draw => self.draw_event(),
+        }
+    }
+
+    /// The outcome note that was published for them when the bet settled.
+    pub fn outcome_event_id(&self) -> Option<EventId> {
+        self.outcome_event_id
+            .as_ref()
+            .map(|b| EventId::from_slice(b).expect("invalid outcome_event_id"))
+    }
+
+    /// Which of their notes was published when the bet settled.
+    pub fn published_note(&self) -> Option<SignedNote> {
+        let id = self.outcome_event_id()?;
+        SignedNote::ALL
+            .into_iter()
+            .find(|n| self.note(*n).is_some_and(|e| e.id == id))
+    }
+
+    pub fn create(
+        conn: &mut PgConnection,
+        bet_id: i32,
+        participant_index: i32,
+        notes: OutcomeNotes,
+        accepted: bool,
+    ) -> anyhow::Result<Self> {
+        let new_participant = NewBetParticipant {
+            bet_id,
+            participant_index,
+            pubkey: notes.win.pubkey.serialize().to_vec(),
+            win_event: serde_json::to_value(notes.win)?,
+            lose_event: serde_json::to_value(notes.lose)?,
+            accepted_at: accepted.then(|| chrono::Utc::now().naive_utc()),
+            draw_event: notes.draw.map(serde_json::to_value).transpose()?,
+        };
+        let res = diesel::insert_into(bet_participants::table)
+            .values(new_participant)
+            .get_result::<Self>(conn)?;
+        Ok(res)
+    }
+
+    /// Everyone taking part in the bet, starting with the proposer.
+    pub fn get_by_bet_id(conn: &mut PgConnection, bet_id: i32) -> anyhow::Result<Vec<Self>> {
+        let res = bet_participants::table
+            .filter(bet_participants::bet_id.eq(bet_id))
+            .order(bet_participants::participant_index.asc())
+            .load::<Self>(conn)?;
+        Ok(res)
+    }
+
+    pub fn set_accepted(conn: &mut PgConnection, id: i32) -> anyhow::Result<()> {
+        diesel::update(bet_participants::table.find(id))
+            .set(bet_participants::accepted_at.eq(chrono::Utc::now().naive_utc()))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn set_outcome_event_id(
+        conn: &mut PgConnection,
+        id: i32,
+        outcome_event_id: EventId,
+    ) -> anyhow::Result<()> {
+        diesel::update(bet_participants::table.find(id))
+            .set(bet_participants::outcome_event_id.eq(outcome_event_id.to_bytes().to_vec()))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Every outcome note that was published, across all bets.
+    pub fn get_outcome_event_ids(conn: &mut PgConnection) -> anyhow::Result<Vec<EventId>> {
+        let res = bet_participants::table
+            .filter(bet_participants::outcome_event_id.is_not_null())
+            .select(bet_participants::outcome_event_id)
+            .load::<Option<Vec<u8>>>(conn)?
+            .into_iter()
+            .flatten()
+            .map(|b| EventId::from_slice(&b).expect("invalid outcome_event_id"))
+            .collect();
+        Ok(res)
+    }
+}
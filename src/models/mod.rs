@@ -1,69 +1,233 @@
-use crate::models::bet::Bet;
-use crate::models::sig::Sig;
+use crate::error::ApiError;
+use crate::models::bet::{Bet, BetStatus, Counterparties, OutcomeNotes};
+use crate::models::bet_oracle::BetOracle;
+use crate::models::bet_participant::BetParticipant;
+use crate::models::sig::{Sig, SignedNote};
+use anyhow::anyhow;
 use diesel::{Connection, PgConnection};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations};
 use dlc_messages::oracle_msgs::OracleAnnouncement;
 use nostr::key::XOnlyPublicKey;
-use nostr::{EventId, UnsignedEvent};
+use nostr::{Event, EventId};
 use schnorr_fun::adaptor::EncryptedSignature;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+pub mod attestation;
 pub mod bet;
+pub mod bet_history;
+pub mod bet_oracle;
+pub mod bet_participant;
+pub mod oracle;
+pub mod oracle_announcement;
+pub mod rejected_attestation;
 mod schema;
 pub mod sig;
+pub mod used_event;
+pub mod user_settings;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
+/// Creates a bet settled by `threshold` of the given oracles, the first of
+/// which is the bet's primary oracle.
 #[allow(clippy::too_many_arguments)]
 pub fn create_bet(
     conn: &mut PgConnection,
-    oracle_announcement: OracleAnnouncement,
-    win_a: UnsignedEvent,
-    lose_a: UnsignedEvent,
-    win_b: UnsignedEvent,
-    lose_b: UnsignedEvent,
-    oracle_event_id: EventId,
-    sigs: HashMap<String, (EncryptedSignature, bool)>,
+    oracles: Vec<(OracleAnnouncement, EventId, bool)>,
+    threshold: i32,
+    notes: OutcomeNotes,
+    counterparties: Counterparties,
+    draw_outcomes: Vec<String>,
+    no_contest_outcomes: Vec<String>,
+    sigs: HashMap<String, (Vec<EncryptedSignature>, SignedNote)>,
+    expires_at: chrono::NaiveDateTime,
 ) -> anyhow::Result<i32> {
+    let (oracle_announcement, oracle_event_id, _) =
+        oracles.first().cloned().ok_or(anyhow!("No oracles"))?;
+    let participant_count = counterparties.participant_count();
+    let (named, templates) = match counterparties {
+        Counterparties::Named(named) => (named, None),
+        Counterparties::Open { templates, .. } => (vec![], Some(templates)),
+    };
     conn.transaction(|conn| {
         let bet = Bet::create(
             conn,
             oracle_announcement,
-            win_a,
-            lose_a,
-            win_b,
-            lose_b,
             oracle_event_id,
+            threshold,
+            expires_at,
+            participant_count as i32,
+            templates,
+            draw_outcomes,
+            no_contest_outcomes,
+            notes.win.pubkey,
         )?;
-        Sig::create_all(conn, bet.id, true, sigs)?;
+        BetOracle::create_all(conn, bet.id, oracles)?;
+        BetParticipant::create(conn, bet.id, 0, notes, true)?;
+        for (index, notes) in named.into_iter().enumerate() {
+            BetParticipant::create(conn, bet.id, index as i32 + 1, notes, false)?;
+        }
+        Sig::create_all(conn, bet.id, 0, sigs)?;
         Ok(bet.id)
     })
 }
 
+/// Adds a named participant's sigs, the bet becomes active once everyone
+/// has added theirs.
 pub fn add_sigs(
     conn: &mut PgConnection,
     bet_id: i32,
-    sigs: HashMap<String, (EncryptedSignature, bool)>,
+    key: XOnlyPublicKey,
+    sigs: HashMap<String, (Vec<EncryptedSignature>, SignedNote)>,
+) -> anyhow::Result<Bet> {
+    conn.transaction(|conn| {
+        let bet = Bet::lock(conn, bet_id)?;
+        let participants = BetParticipant::get_by_bet_id(conn, bet_id)?;
+        let participant = participants
+            .iter()
+            .find(|p| p.pubkey() == key && p.participant_index > 0)
+            .ok_or(ApiError::Forbidden(
+                "only counterparties can add sigs".to_string(),
+            ))?;
+        if participant.accepted_at.is_some() {
+            anyhow::bail!(ApiError::Conflict("sigs were already added".to_string()));
+        }
+
+        join_bet(conn, &bet, &participants, participant, sigs)
+    })
+}
+
+/// Accepts an open bet, adding the acceptor as a participant. Only the first
+/// acceptors get in.
+pub fn accept_bet(
+    conn: &mut PgConnection,
+    bet_id: i32,
+    notes: OutcomeNotes,
+    sigs: HashMap<String, (Vec<EncryptedSignature>, SignedNote)>,
+) -> anyhow::Result<Bet> {
+    conn.transaction(|conn| {
+        let bet = Bet::lock(conn, bet_id)?;
+        let mut participants = BetParticipant::get_by_bet_id(conn, bet_id)?;
+        if participants.len() >= bet.participant_count as usize {
+            anyhow::bail!(ApiError::Conflict("bet is already full".to_string()));
+        }
+        if participants.iter().any(|p| p.pubkey() == notes.win.pubkey) {
+            anyhow::bail!(ApiError::Conflict(
+                "already taking part in the bet".to_string()
+            ));
+        }
+
+        let index = participants.len() as i32;
+        let participant = BetParticipant::create(conn, bet_id, index, notes, false)?;
+        participants.push(participant.clone());
+
+        join_bet(conn, &bet, &participants, &participant, sigs)
+    })
+}
+
+/// Stores a participant's verified sigs, making sure nobody else already
+/// wins on their outcomes. Once the last participant is in, every outcome
+/// that isn't a draw must have a winner and the bet becomes active. Must be
+/// called with the bet locked.
+fn join_bet(
+    conn: &mut PgConnection,
+    bet: &Bet,
+    participants: &[BetParticipant],
+    participant: &BetParticipant,
+    sigs: HashMap<String, (Vec<EncryptedSignature>, SignedNote)>,
+) -> anyhow::Result<Bet> {
+    if bet.status() != BetStatus::Proposed {
+        anyhow::bail!(ApiError::Conflict(format!(
+            "bet is already {}",
+            bet.status().as_str()
+        )));
+    }
+
+    let existing = Sig::get_by_bet_id(conn, bet.id)?;
+    let mut winners = existing
+        .iter()
+        .filter(|s| s.note() == SignedNote::Win)
+        .map(|s| s.outcome.clone())
+        .collect::<HashSet<_>>();
+    for (outcome, (_, note)) in sigs.iter() {
+        if *note == SignedNote::Win && !winners.insert(outcome.clone()) {
+            anyhow::bail!(ApiError::InvalidSigs(format!(
+                "Another participant already wins on outcome {outcome}"
+            )));
+        }
+    }
+
+    Sig::create_all(conn, bet.id, participant.participant_index, sigs)?;
+    BetParticipant::set_accepted(conn, participant.id)?;
+
+    let waiting = participants
+        .iter()
+        .filter(|p| p.id != participant.id && p.accepted_at.is_none())
+        .count();
+    if waiting > 0 || participants.len() < bet.participant_count as usize {
+        return Ok(bet.clone());
+    }
+
+    // draws have no winner
+    if let Some(outcome) = existing
+        .iter()
+        .find(|s| s.note() != SignedNote::Draw && !winners.contains(&s.outcome))
+    {
+        anyhow::bail!(ApiError::InvalidSigs(format!(
+            "Nobody wins on outcome {}",
+            outcome.outcome
+        )));
+    }
+
+    Bet::transition(
+        conn,
+        bet.id,
+        BetStatus::Active,
+        Some(participant.pubkey()),
+        None,
+    )
+}
+
+/// Rejects a bet that hasn't been accepted yet, keeping it around with who
+/// rejected it.
+pub fn reject_bet(
+    conn: &mut PgConnection,
+    bet_id: i32,
+    key: XOnlyPublicKey,
+    event: &Event,
 ) -> anyhow::Result<Bet> {
     conn.transaction(|conn| {
-        Sig::create_all(conn, bet_id, false, sigs)?;
-        let bet = Bet::set_needs_reply(conn, bet_id)?;
-        Ok(bet)
+        Bet::get_by_id(conn, bet_id)?.ok_or(ApiError::NotFound("bet not found".to_string()))?;
+
+        let participants = BetParticipant::get_by_bet_id(conn, bet_id)?;
+        if !participants.iter().any(|p| p.pubkey() == key) {
+            anyhow::bail!(ApiError::Forbidden(
+                "only participants can reject a bet".to_string()
+            ));
+        }
+
+        Bet::set_rejected(conn, bet_id, key, event)
     })
 }
 
-pub fn reject_bet(conn: &mut PgConnection, bet_id: i32, key: XOnlyPublicKey) -> anyhow::Result<()> {
+/// Lets the proposer withdraw a bet that hasn't been accepted yet.
+pub fn cancel_bet(
+    conn: &mut PgConnection,
+    bet_id: i32,
+    key: XOnlyPublicKey,
+    event: &Event,
+) -> anyhow::Result<Bet> {
     conn.transaction(|conn| {
-        let event = Bet::get_by_id(conn, bet_id)?;
+        Bet::get_by_id(conn, bet_id)?.ok_or(ApiError::NotFound("bet not found".to_string()))?;
 
-        if let Some(bet) = event {
-            if bet.user_a() == key || bet.user_b() == key {
-                Sig::delete_by_bet_id(conn, bet_id)?;
-                Bet::delete_by_bet_id(conn, bet_id)?;
-            }
+        let participants = BetParticipant::get_by_bet_id(conn, bet_id)?;
+        if participants.first().map(|p| p.pubkey()) != Some(key) {
+            anyhow::bail!(ApiError::Forbidden(
+                "only the proposer can cancel a bet".to_string()
+            ));
         }
-        Ok(())
+
+        Bet::transition(conn, bet_id, BetStatus::Cancelled, Some(key), Some(event))
     })
 }
 
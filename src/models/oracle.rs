@@ -0,0 +1,140 @@
+use super::schema::oracles;
+use diesel::prelude::*;
+use nostr::key::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How much the server trusts a registered oracle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustStatus {
+    /// Allowed even when only allowlisted oracles are accepted
+    Trusted,
+    /// Known, but not allowlisted
+    Neutral,
+    /// Never accepted unless any oracle is allowed
+    Blocked,
+}
+
+impl TrustStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrustStatus::Trusted => "trusted",
+            TrustStatus::Neutral => "neutral",
+            TrustStatus::Blocked => "blocked",
+        }
+    }
+}
+
+impl FromStr for TrustStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trusted" => Ok(TrustStatus::Trusted),
+            "neutral" => Ok(TrustStatus::Neutral),
+            "blocked" => Ok(TrustStatus::Blocked),
+            _ => Err(anyhow::anyhow!("invalid trust status: {s}")),
+        }
+    }
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    AsChangeset,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+)]
+#[diesel(primary_key(id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Oracle {
+    pub id: i32,
+    pubkey: Vec<u8>,
+    pub name: String,
+    pub description: Option<String>,
+    pub relays: Vec<String>,
+    trust_status: String,
+    created_at: chrono::NaiveDateTime,
+    updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = oracles)]
+#[diesel(treat_none_as_null = true)]
+struct NewOracle {
+    pubkey: Vec<u8>,
+    name: String,
+    description: Option<String>,
+    relays: Vec<String>,
+    trust_status: String,
+    updated_at: chrono::NaiveDateTime,
+}
+
+impl Oracle {
+    pub fn pubkey(&self) -> XOnlyPublicKey {
+        XOnlyPublicKey::from_slice(&self.pubkey).expect("invalid pubkey")
+    }
+
+    pub fn trust_status(&self) -> TrustStatus {
+        TrustStatus::from_str(&self.trust_status).expect("invalid trust status")
+    }
+
+    /// Adds the oracle to the registry, or replaces its details if it is
+    /// already registered.
+    pub fn upsert(
+        conn: &mut PgConnection,
+        pubkey: XOnlyPublicKey,
+        name: String,
+        description: Option<String>,
+        relays: Vec<String>,
+        trust_status: TrustStatus,
+    ) -> anyhow::Result<Self> {
+        let new = NewOracle {
+            pubkey: pubkey.serialize().to_vec(),
+            name,
+            description,
+            relays,
+            trust_status: trust_status.as_str().to_string(),
+            updated_at: chrono::Utc::now().naive_utc(),
+        };
+        let res = diesel::insert_into(oracles::table)
+            .values(&new)
+            .on_conflict(oracles::pubkey)
+            .do_update()
+            .set(&new)
+            .get_result(conn)?;
+
+        Ok(res)
+    }
+
+    pub fn get_all(conn: &mut PgConnection) -> anyhow::Result<Vec<Self>> {
+        let res = oracles::table.order(oracles::name.asc()).load(conn)?;
+        Ok(res)
+    }
+
+    pub fn get_by_pubkey(
+        conn: &mut PgConnection,
+        pubkey: XOnlyPublicKey,
+    ) -> anyhow::Result<Option<Self>> {
+        let res = oracles::table
+            .filter(oracles::pubkey.eq(pubkey.serialize().to_vec()))
+            .first(conn)
+            .optional()?;
+
+        Ok(res)
+    }
+
+    /// Removes the oracle from the registry, returning whether it was there.
+    pub fn delete(conn: &mut PgConnection, pubkey: XOnlyPublicKey) -> anyhow::Result<bool> {
+        let deleted =
+            diesel::delete(oracles::table.filter(oracles::pubkey.eq(pubkey.serialize().to_vec())))
+                .execute(conn)?;
+        Ok(deleted > 0)
+    }
+}
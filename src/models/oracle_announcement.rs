@@ -0,0 +1,72 @@
+use super::schema::oracle_announcements;
+use diesel::prelude::*;
+use dlc_messages::oracle_msgs::OracleAnnouncement;
+use lightning::util::ser::{Readable, Writeable};
+use nostr::EventId;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+/// An oracle announcement resolved from its nostr event and validated.
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    AsChangeset,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+)]
+#[diesel(primary_key(id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct CachedAnnouncement {
+    pub id: i32,
+    oracle_event_id: Vec<u8>,
+    announcement: Vec<u8>,
+    created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = oracle_announcements)]
+struct NewCachedAnnouncement {
+    oracle_event_id: Vec<u8>,
+    announcement: Vec<u8>,
+}
+
+impl CachedAnnouncement {
+    pub fn announcement(&self) -> OracleAnnouncement {
+        let mut cursor = Cursor::new(&self.announcement);
+        OracleAnnouncement::read(&mut cursor).expect("invalid oracle announcement")
+    }
+
+    pub fn create(
+        conn: &mut PgConnection,
+        oracle_event_id: EventId,
+        announcement: &OracleAnnouncement,
+    ) -> anyhow::Result<()> {
+        let new = NewCachedAnnouncement {
+            oracle_event_id: oracle_event_id.to_bytes().to_vec(),
+            announcement: announcement.encode(),
+        };
+        diesel::insert_into(oracle_announcements::table)
+            .values(new)
+            .on_conflict(oracle_announcements::oracle_event_id)
+            .do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn get_by_oracle_event(
+        conn: &mut PgConnection,
+        oracle_event_id: &EventId,
+    ) -> anyhow::Result<Option<Self>> {
+        let res = oracle_announcements::table
+            .filter(oracle_announcements::oracle_event_id.eq(oracle_event_id.to_bytes().to_vec()))
+            .first(conn)
+            .optional()?;
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,37 @@
+use super::schema::rejected_attestations;
+use diesel::prelude::*;
+use nostr::{Event, EventId};
+
+#[derive(Insertable)]
+#[diesel(table_name = rejected_attestations)]
+struct NewRejectedAttestation {
+    event_id: Vec<u8>,
+    oracle_event_id: Vec<u8>,
+    pubkey: Vec<u8>,
+    content: String,
+    reason: String,
+}
+
+/// Audit log of attestation events that failed verification.
+pub struct RejectedAttestation;
+
+impl RejectedAttestation {
+    pub fn create(
+        conn: &mut PgConnection,
+        event: &Event,
+        oracle_event_id: EventId,
+        reason: &str,
+    ) -> anyhow::Result<()> {
+        let new = NewRejectedAttestation {
+            event_id: event.id.to_bytes().to_vec(),
+            oracle_event_id: oracle_event_id.to_bytes().to_vec(),
+            pubkey: event.pubkey.serialize().to_vec(),
+            content: event.content.clone(),
+            reason: reason.to_string(),
+        };
+        diesel::insert_into(rejected_attestations::table)
+            .values(new)
+            .execute(conn)?;
+        Ok(())
+    }
+}
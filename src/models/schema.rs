@@ -1,19 +1,106 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    bet_history (id) {
+        id -> Int4,
+        bet_id -> Int4,
+        from_status -> Nullable<Text>,
+        to_status -> Text,
+        actor -> Nullable<Bytea>,
+        event -> Nullable<Jsonb>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    bet_participants (id) {
+        id -> Int4,
+        bet_id -> Int4,
+        participant_index -> Int4,
+        pubkey -> Bytea,
+        win_event -> Jsonb,
+        lose_event -> Jsonb,
+        accepted_at -> Nullable<Timestamp>,
+        outcome_event_id -> Nullable<Bytea>,
+        created_at -> Timestamp,
+        draw_event -> Nullable<Jsonb>,
+    }
+}
+
+diesel::table! {
+    bet_oracles (id) {
+        id -> Int4,
+        bet_id -> Int4,
+        oracle_index -> Int4,
+        oracle_announcement -> Bytea,
+        oracle_event_id -> Bytea,
+        announcement_verified -> Bool,
+    }
+}
+
 diesel::table! {
     bets (id) {
         id -> Int4,
         oracle_announcement -> Bytea,
-        user_a -> Bytea,
-        win_a -> Jsonb,
-        lose_a -> Jsonb,
-        user_b -> Bytea,
-        win_b -> Jsonb,
-        lose_b -> Jsonb,
         oracle_event_id -> Bytea,
-        needs_reply -> Bool,
-        win_outcome_event_id -> Nullable<Bytea>,
-        lose_outcome_event_id -> Nullable<Bytea>,
+        created_at -> Timestamp,
+        threshold -> Int4,
+        expires_at -> Timestamp,
+        expired_at -> Nullable<Timestamp>,
+        voided_at -> Nullable<Timestamp>,
+        settlement_error -> Nullable<Text>,
+        status -> Text,
+        rejected_by -> Nullable<Bytea>,
+        rejected_at -> Nullable<Timestamp>,
+        reject_event -> Nullable<Jsonb>,
+        counterparty_win_template -> Nullable<Jsonb>,
+        counterparty_lose_template -> Nullable<Jsonb>,
+        participant_count -> Int4,
+        counterparty_draw_template -> Nullable<Jsonb>,
+        draw_outcomes -> Array<Text>,
+        no_contest_outcomes -> Array<Text>,
+    }
+}
+
+diesel::table! {
+    oracle_announcements (id) {
+        id -> Int4,
+        oracle_event_id -> Bytea,
+        announcement -> Bytea,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    oracle_attestations (id) {
+        id -> Int4,
+        oracle_event_id -> Bytea,
+        attestation -> Bytea,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    oracles (id) {
+        id -> Int4,
+        pubkey -> Bytea,
+        name -> Text,
+        description -> Nullable<Text>,
+        relays -> Array<Text>,
+        trust_status -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    rejected_attestations (id) {
+        id -> Int4,
+        event_id -> Bytea,
+        oracle_event_id -> Bytea,
+        pubkey -> Bytea,
+        content -> Text,
+        reason -> Text,
         created_at -> Timestamp,
     }
 }
@@ -22,16 +109,47 @@ diesel::table! {
     sigs (id) {
         id -> Int4,
         bet_id -> Int4,
-        is_party_a -> Bool,
         is_win -> Bool,
         sig -> Bytea,
         outcome -> Text,
+        oracle_combination -> Int4,
+        participant_index -> Int4,
+        is_draw -> Bool,
+    }
+}
+
+diesel::table! {
+    used_events (event_id) {
+        event_id -> Bytea,
+        pubkey -> Bytea,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_settings (pubkey) {
+        pubkey -> Bytea,
+        dm_opt_out -> Bool,
+        updated_at -> Timestamp,
+        private -> Bool,
     }
 }
 
+diesel::joinable!(bet_history -> bets (bet_id));
+diesel::joinable!(bet_oracles -> bets (bet_id));
+diesel::joinable!(bet_participants -> bets (bet_id));
 diesel::joinable!(sigs -> bets (bet_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    bet_history,
+    bet_oracles,
+    bet_participants,
     bets,
+    oracle_announcements,
+    oracle_attestations,
+    oracles,
+    rejected_attestations,
     sigs,
+    used_events,
+    user_settings,
 );
@@ -24,20 +24,45 @@ use std::collections::HashMap;
 pub struct Sig {
     pub id: i32,
     pub bet_id: i32,
-    pub is_party_a: bool,
     pub is_win: bool,
     sig: Vec<u8>,
     pub outcome: String,
+    pub oracle_combination: i32,
+    pub participant_index: i32,
+    pub is_draw: bool,
+}
+
+/// Which of a participant's outcome notes a sig is for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignedNote {
+    Win,
+    Lose,
+    Draw,
+}
+
+impl SignedNote {
+    pub const ALL: [SignedNote; 3] = [SignedNote::Win, SignedNote::Lose, SignedNote::Draw];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignedNote::Win => "win",
+            SignedNote::Lose => "lose",
+            SignedNote::Draw => "draw",
+        }
+    }
 }
 
 #[derive(Insertable, AsChangeset)]
 #[diesel(table_name = sigs)]
 struct NewSig {
     bet_id: i32,
-    is_party_a: bool,
     is_win: bool,
     sig: Vec<u8>,
     outcome: String,
+    oracle_combination: i32,
+    participant_index: i32,
+    is_draw: bool,
 }
 
 impl Sig {
@@ -45,26 +70,42 @@ impl Sig {
         bincode::deserialize(&self.sig).expect("invalid sig")
     }
 
+    pub fn note(&self) -> SignedNote {
+        match (self.is_win, self.is_draw) {
+            (_, true) => SignedNote::Draw,
+            (true, false) => SignedNote::Win,
+            (false, false) => SignedNote::Lose,
+        }
+    }
+
+    /// Stores the sigs for each outcome, one per oracle combination.
     pub fn create_all(
         conn: &mut PgConnection,
         bet_id: i32,
-        is_party_a: bool,
-        sigs: HashMap<String, (EncryptedSignature, bool)>,
-    ) -> anyhow::Result<Self> {
+        participant_index: i32,
+        sigs: HashMap<String, (Vec<EncryptedSignature>, SignedNote)>,
+    ) -> anyhow::Result<()> {
         let new_sigs = sigs
             .into_iter()
-            .map(|(outcome, (sig, is_win))| NewSig {
-                bet_id,
-                is_party_a,
-                is_win,
-                sig: bincode::serialize(&sig).expect("invalid sig"),
-                outcome,
+            .flat_map(|(outcome, (sigs, note))| {
+                sigs.into_iter()
+                    .enumerate()
+                    .map(move |(combination, sig)| NewSig {
+                        bet_id,
+                        is_win: note == SignedNote::Win,
+                        sig: bincode::serialize(&sig).expect("invalid sig"),
+                        outcome: outcome.clone(),
+                        oracle_combination: combination as i32,
+                        participant_index,
+                        is_draw: note == SignedNote::Draw,
+                    })
             })
             .collect::<Vec<_>>();
 
-        Ok(diesel::insert_into(sigs::table)
+        diesel::insert_into(sigs::table)
             .values(new_sigs)
-            .get_result(conn)?)
+            .execute(conn)?;
+        Ok(())
     }
 
     pub fn get_by_bet_id(conn: &mut PgConnection, bet_id: i32) -> anyhow::Result<Vec<Self>> {
@@ -73,21 +114,18 @@ impl Sig {
         Ok(res)
     }
 
-    pub fn delete_by_bet_id(conn: &mut PgConnection, bet_id: i32) -> anyhow::Result<()> {
-        diesel::delete(sigs::table.filter(sigs::bet_id.eq(bet_id))).execute(conn)?;
-        Ok(())
-    }
-
     pub fn get_by_params(
         conn: &mut PgConnection,
         bet_id: i32,
         outcome: &str,
-        is_party_a: bool,
+        participant_index: i32,
+        oracle_combination: i32,
     ) -> anyhow::Result<Option<Self>> {
         let res = sigs::table
             .filter(sigs::bet_id.eq(bet_id))
             .filter(sigs::outcome.eq(outcome))
-            .filter(sigs::is_party_a.eq(is_party_a))
+            .filter(sigs::participant_index.eq(participant_index))
+            .filter(sigs::oracle_combination.eq(oracle_combination))
             .first(conn)
             .optional()?;
 
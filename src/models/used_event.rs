@@ -0,0 +1,49 @@
+use super::schema::used_events;
+use crate::error::ApiError;
+use diesel::prelude::*;
+use nostr::key::XOnlyPublicKey;
+use nostr::EventId;
+
+#[derive(Insertable)]
+#[diesel(table_name = used_events)]
+struct NewUsedEvent {
+    event_id: Vec<u8>,
+    pubkey: Vec<u8>,
+}
+
+/// Signed events the server has already accepted, so they can't be replayed.
+pub struct UsedEvent;
+
+impl UsedEvent {
+    /// Records the event as used, failing if it already was.
+    pub fn mark_used(
+        conn: &mut PgConnection,
+        event_id: EventId,
+        pubkey: XOnlyPublicKey,
+    ) -> anyhow::Result<()> {
+        let new = NewUsedEvent {
+            event_id: event_id.to_bytes().to_vec(),
+            pubkey: pubkey.serialize().to_vec(),
+        };
+        let inserted = diesel::insert_into(used_events::table)
+            .values(new)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        if inserted == 0 {
+            anyhow::bail!(ApiError::Conflict("event already used".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Forgets events used before the cutoff, they are too old to be accepted
+    /// again anyway.
+    pub fn delete_before(
+        conn: &mut PgConnection,
+        cutoff: chrono::NaiveDateTime,
+    ) -> anyhow::Result<usize> {
+        let res = diesel::delete(used_events::table.filter(used_events::created_at.lt(cutoff)))
+            .execute(conn)?;
+        Ok(res)
+    }
+}
@@ -0,0 +1,85 @@
+use super::schema::user_settings;
+use diesel::prelude::*;
+use nostr::key::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    AsChangeset,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+)]
+#[diesel(primary_key(pubkey))]
+#[diesel(table_name = user_settings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserSettings {
+    pubkey: Vec<u8>,
+    pub dm_opt_out: bool,
+    updated_at: chrono::NaiveDateTime,
+    /// Left out of the bets the server publishes
+    pub private: bool,
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = user_settings)]
+struct NewUserSettings {
+    pubkey: Vec<u8>,
+    dm_opt_out: bool,
+    updated_at: chrono::NaiveDateTime,
+    private: bool,
+}
+
+impl UserSettings {
+    pub fn get(conn: &mut PgConnection, pubkey: XOnlyPublicKey) -> anyhow::Result<Option<Self>> {
+        let res = user_settings::table
+            .find(pubkey.serialize().to_vec())
+            .first(conn)
+            .optional()?;
+
+        Ok(res)
+    }
+
+    /// Whether the user wants DM notifications, they do unless they opted out.
+    pub fn wants_dms(conn: &mut PgConnection, pubkey: XOnlyPublicKey) -> anyhow::Result<bool> {
+        Ok(!Self::get(conn, pubkey)?.is_some_and(|s| s.dm_opt_out))
+    }
+
+    /// Whether the user should be left out of published bets.
+    pub fn is_private(conn: &mut PgConnection, pubkey: XOnlyPublicKey) -> anyhow::Result<bool> {
+        Ok(Self::get(conn, pubkey)?.is_some_and(|s| s.private))
+    }
+
+    /// Updates the given settings, keeping the current value of the others.
+    pub fn update(
+        conn: &mut PgConnection,
+        pubkey: XOnlyPublicKey,
+        dm_opt_out: Option<bool>,
+        private: Option<bool>,
+    ) -> anyhow::Result<Self> {
+        let current = Self::get(conn, pubkey)?;
+        let new = NewUserSettings {
+            pubkey: pubkey.serialize().to_vec(),
+            dm_opt_out: dm_opt_out
+                .or(current.as_ref().map(|s| s.dm_opt_out))
+                .unwrap_or(false),
+            updated_at: chrono::Utc::now().naive_utc(),
+            private: private
+                .or(current.as_ref().map(|s| s.private))
+                .unwrap_or(false),
+        };
+        let res = diesel::insert_into(user_settings::table)
+            .values(&new)
+            .on_conflict(user_settings::pubkey)
+            .do_update()
+            .set(&new)
+            .get_result(conn)?;
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,207 @@
+use crate::models::bet_participant::BetParticipant;
+use crate::models::sig::SignedNote;
+use crate::models::user_settings::UserSettings;
+use crate::updates::{BetUpdate, Notification};
+use crate::State;
+use log::{debug, error, warn};
+use nostr::key::XOnlyPublicKey;
+use nostr::nips::nip44::{self, Version};
+use nostr::secp256k1::rand::{thread_rng, Rng};
+use nostr::{Event, EventBuilder, JsonUtil, Keys, Kind, Tag, Timestamp};
+use std::collections::HashMap;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Kind of NIP-17 direct messages
+const DM_KIND: u64 = 14;
+/// Kind of NIP-59 seals
+const SEAL_KIND: u64 = 13;
+/// Kind of NIP-59 gift wraps
+const GIFT_WRAP_KIND: u64 = 1059;
+/// How far back seals and gift wraps are dated, so they can't be linked to
+/// the updates they're about by timing
+const MAX_TIMESTAMP_TWEAK_SECS: u64 = 2 * 24 * 60 * 60;
+
+/// Sends participants a DM for the bet updates they would want to hear about,
+/// unless they opted out.
+pub async fn start_dm_task(state: State) {
+    let mut receiver = state.updates.subscribe();
+    loop {
+        match receiver.recv().await {
+            Ok(notification) => {
+                if let Err(e) = send_dms(&state, notification).await {
+                    error!("Error sending DM notifications: {e}");
+                }
+            }
+            Err(RecvError::Lagged(missed)) => warn!("Missed {missed} DM notifications"),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// Who should be told about the update and what to tell them. The proposer
+/// is always the first participant.
+fn message(notification: &Notification) -> Option<(&[XOnlyPublicKey], String)> {
+    let participants = notification.participants.as_slice();
+    match notification.update {
+        BetUpdate::Proposed { bet_id } => Some((
+            participants.get(1..)?,
+            format!(
+                "You have a new bet proposal from {}, bet {bet_id}",
+                participants.first()?
+            ),
+        )),
+        BetUpdate::Accepted { bet_id } => Some((
+            participants.get(..1)?,
+            format!("Your bet {bet_id} was accepted"),
+        )),
+        BetUpdate::Rejected { bet_id } => {
+            Some((participants, format!("Bet {bet_id} was rejected")))
+        }
+        BetUpdate::Attested {
+            bet_id,
+            oracle_event_id,
+        } => Some((
+            participants,
+            format!("The oracle attested to {oracle_event_id} for bet {bet_id}"),
+        )),
+        BetUpdate::Settled { bet_id, .. } => Some((participants, format!("Bet {bet_id} settled"))),
+        BetUpdate::Drawn { bet_id, .. } => {
+            Some((participants, format!("Bet {bet_id} ended in a draw")))
+        }
+        BetUpdate::NoContest { bet_id } => Some((
+            participants,
+            format!("Bet {bet_id} ended with no contest, no outcome notes were published"),
+        )),
+        BetUpdate::Voided { bet_id } => Some((
+            participants,
+            format!("Bet {bet_id} was voided, its outcome notes will never be published"),
+        )),
+        BetUpdate::Cancelled { .. } | BetUpdate::Expired { .. } => None,
+    }
+}
+
+async fn send_dms(state: &State, notification: Notification) -> anyhow::Result<()> {
+    let Some((recipients, content)) = message(&notification) else {
+        return Ok(());
+    };
+
+    // only the notes of participants with sigs for the winning combination
+    // get published, tell each of them whether theirs was
+    let published = match notification.update {
+        BetUpdate::Settled { bet_id, .. } | BetUpdate::Drawn { bet_id, .. } => {
+            let mut conn = state.db_pool.get()?;
+            let published = BetParticipant::get_by_bet_id(&mut conn, bet_id)?
+                .into_iter()
+                .map(|p| (p.pubkey(), p.published_note()))
+                .collect::<HashMap<_, _>>();
+            Some(published)
+        }
+        _ => None,
+    };
+
+    for recipient in recipients {
+        let mut conn = state.db_pool.get()?;
+        if !UserSettings::wants_dms(&mut conn, *recipient)? {
+            continue;
+        }
+        drop(conn);
+
+        let content = match published.as_ref() {
+            Some(published) => {
+                let note = published.get(recipient).copied().flatten();
+                published_note_message(&content, note)
+            }
+            None => content.clone(),
+        };
+        let dm = gift_wrap(&state.keys, *recipient, &content)?;
+        let event_id = state.nostr_client.send_event(dm).await?;
+        debug!("Sent DM {event_id} to {recipient}");
+    }
+
+    Ok(())
+}
+
+/// Tells a participant of a finished bet whether their outcome note was
+/// published.
+fn published_note_message(message: &str, note: Option<SignedNote>) -> String {
+    match note {
+        Some(note) => format!("{message}, your {} note was published", note.as_str()),
+        None => format!("{message}, none of your outcome notes were published"),
+    }
+}
+
+/// A random time up to two days ago, as NIP-59 asks seals and gift wraps to
+/// be dated.
+fn tweaked_timestamp() -> Timestamp {
+    let tweak = thread_rng().gen_range(0..=MAX_TIMESTAMP_TWEAK_SECS);
+    Timestamp::from(Timestamp::now().as_u64() - tweak)
+}
+
+/// Wraps the message as a NIP-17 DM, sealed with the server's keys and gift
+/// wrapped with a one-off key so only the recipient can see who it's from.
+fn gift_wrap(keys: &Keys, recipient: XOnlyPublicKey, content: &str) -> anyhow::Result<Event> {
+    let rumor = EventBuilder::new(Kind::Custom(DM_KIND), content, [Tag::public_key(recipient)])
+        .to_unsigned_event(keys.public_key());
+
+    let sealed = nip44::encrypt(
+        &keys.secret_key()?,
+        &recipient,
+        rumor.as_json(),
+        Version::V2,
+    )?;
+    let seal = EventBuilder::new(Kind::Custom(SEAL_KIND), sealed, [])
+        .custom_created_at(tweaked_timestamp())
+        .to_event(keys)?;
+
+    let wrap_keys = Keys::generate();
+    let wrapped = nip44::encrypt(
+        &wrap_keys.secret_key()?,
+        &recipient,
+        seal.as_json(),
+        Version::V2,
+    )?;
+    let wrap = EventBuilder::new(
+        Kind::Custom(GIFT_WRAP_KIND),
+        wrapped,
+        [Tag::public_key(recipient)],
+    )
+    .custom_created_at(tweaked_timestamp())
+    .to_event(&wrap_keys)?;
+
+    Ok(wrap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_participants_which_note_was_published() {
+        assert_eq!(
+            published_note_message("Bet 1 settled", Some(SignedNote::Win)),
+            "Bet 1 settled, your win note was published"
+        );
+        assert_eq!(
+            published_note_message("Bet 1 ended in a draw", Some(SignedNote::Draw)),
+            "Bet 1 ended in a draw, your draw note was published"
+        );
+    }
+
+    #[test]
+    fn tells_participants_without_sigs_nothing_was_published() {
+        assert_eq!(
+            published_note_message("Bet 1 settled", None),
+            "Bet 1 settled, none of your outcome notes were published"
+        );
+    }
+
+    #[test]
+    fn timestamps_are_tweaked_into_the_past() {
+        for _ in 0..100 {
+            let before = Timestamp::now().as_u64();
+            let tweaked = tweaked_timestamp().as_u64();
+            assert!(tweaked <= Timestamp::now().as_u64());
+            assert!(tweaked >= before - MAX_TIMESTAMP_TWEAK_SECS);
+        }
+    }
+}
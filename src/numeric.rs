@@ -0,0 +1,245 @@
+use crate::error::ApiError;
+use dlc_messages::oracle_msgs::DigitDecompositionEventDescriptor;
+use serde::{Deserialize, Serialize};
+
+/// An inclusive range of values a digit decomposition event can attest to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NumericRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Returns the largest value the event can attest to.
+pub fn max_value(desc: &DigitDecompositionEventDescriptor) -> anyhow::Result<u64> {
+    if desc.is_signed {
+        anyhow::bail!(ApiError::UnsupportedEvent(
+            "Signed numeric events are not supported".to_string()
+        ));
+    }
+    if desc.base < 2 || desc.nb_digits == 0 {
+        anyhow::bail!(ApiError::UnsupportedEvent(
+            "Invalid numeric event descriptor".to_string()
+        ));
+    }
+
+    let max = (desc.base as u64)
+        .checked_pow(desc.nb_digits as u32)
+        .map(|v| v - 1)
+        .ok_or(ApiError::UnsupportedEvent(
+            "Numeric event domain too large".to_string(),
+        ))?;
+    Ok(max)
+}
+
+/// Splits the range into the minimal set of digit prefixes that cover it.
+pub fn decompose(range: NumericRange, base: u64, nb_digits: usize) -> Vec<Vec<u64>> {
+    let mut prefixes = vec![];
+    let mut start = range.start;
+    loop {
+        // grow the block while it stays aligned and inside the range
+        let mut block = 1u64;
+        let mut ignored = 0;
+        while ignored < nb_digits {
+            let next = block * base;
+            if start % next != 0 || range.end - start < next - 1 {
+                break;
+            }
+            block = next;
+            ignored += 1;
+        }
+
+        prefixes.push(to_digits(start / block, base, nb_digits - ignored));
+
+        if range.end - start == block - 1 {
+            break;
+        }
+        start += block;
+    }
+
+    prefixes
+}
+
+/// Returns the parts of `[0, max]` that are not covered by the range.
+pub fn complement(range: NumericRange, max: u64) -> Vec<NumericRange> {
+    let mut ranges = vec![];
+    if range.start > 0 {
+        ranges.push(NumericRange {
+            start: 0,
+            end: range.start - 1,
+        });
+    }
+    if range.end < max {
+        ranges.push(NumericRange {
+            start: range.end + 1,
+            end: max,
+        });
+    }
+    ranges
+}
+
+fn to_digits(mut value: u64, base: u64, len: usize) -> Vec<u64> {
+    let mut digits = vec![0; len];
+    for digit in digits.iter_mut().rev() {
+        *digit = value % base;
+        value /= base;
+    }
+    digits
+}
+
+/// Formats a digit prefix as the outcome string its sig is stored under.
+pub fn prefix_to_outcome(prefix: &[u64]) -> String {
+    prefix
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses an outcome string back into its digit prefix.
+pub fn outcome_to_prefix(outcome: &str) -> anyhow::Result<Vec<u64>> {
+    outcome
+        .split(',')
+        .map(|d| {
+            d.parse::<u64>().map_err(|_| {
+                ApiError::InvalidSigs(format!("Invalid numeric outcome: {outcome}")).into()
+            })
+        })
+        .collect()
+}
+
+/// Finds the stored prefix outcome that matches the attested digits.
+pub fn find_attested_prefix<'a>(
+    outcomes: impl IntoIterator<Item = &'a String>,
+    attested: &[String],
+) -> Option<(&'a String, usize)> {
+    outcomes.into_iter().find_map(|outcome| {
+        let prefix = outcome.split(',').collect::<Vec<_>>();
+        let matches = prefix.len() <= attested.len()
+            && prefix.iter().zip(attested).all(|(p, a)| *p == a.as_str());
+        matches.then_some((outcome, prefix.len()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u64, end: u64) -> NumericRange {
+        NumericRange { start, end }
+    }
+
+    /// Every value covered by the prefixes, in order.
+    fn covered(prefixes: &[Vec<u64>], base: u64, nb_digits: usize) -> Vec<u64> {
+        let mut values = vec![];
+        for prefix in prefixes {
+            let value = prefix.iter().fold(0, |acc, d| acc * base + d);
+            let block = base.pow((nb_digits - prefix.len()) as u32);
+            values.extend(value * block..(value + 1) * block);
+        }
+        values
+    }
+
+    #[test]
+    fn decompose_whole_domain_is_empty_prefix() {
+        assert_eq!(decompose(range(0, 99), 10, 2), vec![Vec::<u64>::new()]);
+    }
+
+    #[test]
+    fn decompose_single_value() {
+        assert_eq!(decompose(range(42, 42), 10, 2), vec![vec![4, 2]]);
+    }
+
+    #[test]
+    fn decompose_uses_minimal_prefixes() {
+        assert_eq!(
+            decompose(range(15, 32), 10, 2),
+            vec![
+                vec![1, 5],
+                vec![1, 6],
+                vec![1, 7],
+                vec![1, 8],
+                vec![1, 9],
+                vec![2],
+                vec![3, 0],
+                vec![3, 1],
+                vec![3, 2],
+            ]
+        );
+        assert_eq!(decompose(range(4, 15), 2, 4), vec![vec![0, 1], vec![1]]);
+    }
+
+    #[test]
+    fn decompose_covers_exactly_the_range() {
+        for (base, nb_digits) in [(2, 5), (3, 3), (10, 2)] {
+            let max = base.pow(nb_digits as u32) - 1;
+            for start in 0..=max {
+                for end in start..=max {
+                    let prefixes = decompose(range(start, end), base, nb_digits);
+                    assert_eq!(
+                        covered(&prefixes, base, nb_digits),
+                        (start..=end).collect::<Vec<_>>(),
+                        "base {base}, range {start}..={end}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn complement_of_range() {
+        assert_eq!(complement(range(0, 99), 99), vec![]);
+        assert_eq!(complement(range(0, 9), 99), vec![range(10, 99)]);
+        assert_eq!(complement(range(90, 99), 99), vec![range(0, 89)]);
+        assert_eq!(
+            complement(range(10, 19), 99),
+            vec![range(0, 9), range(20, 99)]
+        );
+    }
+
+    #[test]
+    fn range_and_complement_cover_the_domain() {
+        let (base, nb_digits, max) = (2, 4, 15);
+        for start in 0..=max {
+            for end in start..=max {
+                let win = decompose(range(start, end), base, nb_digits);
+                let mut values = covered(&win, base, nb_digits);
+                for lose in complement(range(start, end), max) {
+                    values.extend(covered(&decompose(lose, base, nb_digits), base, nb_digits));
+                }
+                values.sort();
+                assert_eq!(values, (0..=max).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn outcome_round_trip() {
+        let prefix = vec![1, 0, 7];
+        assert_eq!(prefix_to_outcome(&prefix), "1,0,7");
+        assert_eq!(outcome_to_prefix("1,0,7").unwrap(), prefix);
+        assert!(outcome_to_prefix("1,x").is_err());
+    }
+
+    #[test]
+    fn attested_prefix_matches_leading_digits() {
+        let outcomes = ["1,5".to_string(), "2".to_string(), "3,0".to_string()];
+        fn attested(digits: &[&str]) -> Vec<String> {
+            digits.iter().map(|d| d.to_string()).collect()
+        }
+
+        assert_eq!(
+            find_attested_prefix(&outcomes, &attested(&["2", "7"])),
+            Some((&outcomes[1], 1))
+        );
+        assert_eq!(
+            find_attested_prefix(&outcomes, &attested(&["1", "5"])),
+            Some((&outcomes[0], 2))
+        );
+        assert_eq!(
+            find_attested_prefix(&outcomes, &attested(&["1", "4"])),
+            None
+        );
+        // the prefix can't be longer than what was attested
+        assert_eq!(find_attested_prefix(&outcomes, &attested(&["3"])), None);
+    }
+}
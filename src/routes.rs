@@ -1,38 +1,147 @@
-use crate::models::bet::Bet;
-use crate::models::sig::Sig;
+use crate::broadcast::BroadcastStats;
+use crate::config::{OraclePolicy, UnauthenticatedListing};
+use crate::error::ApiError;
+use crate::models::bet::{
+    Bet, BetStatus, Counterparties, NoteTemplate, NoteTemplates, OutcomeNotes,
+};
+use crate::models::bet_history::BetHistory;
+use crate::models::bet_oracle::BetOracle;
+use crate::models::bet_participant::BetParticipant;
+use crate::models::oracle::{Oracle, TrustStatus};
+use crate::models::sig::{Sig, SignedNote};
+use crate::models::used_event::UsedEvent;
+use crate::models::user_settings::UserSettings;
 use crate::models::Counts;
-use crate::{models, utils, State};
+use crate::numeric::NumericRange;
+use crate::updates::BetUpdate;
+use crate::{announcement, auth, models, numeric, updates, utils, validation, State};
 use anyhow::anyhow;
+use axum::body::Bytes;
 use axum::extract::Query;
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, Method, Uri};
+use axum::response::sse::{self, KeepAlive, Sse};
 use axum::{Extension, Json};
+use diesel::PgConnection;
 use dlc::secp256k1_zkp::hashes::sha256;
 use dlc::OracleInfo;
-use dlc_messages::oracle_msgs::EventDescriptor;
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement};
+use futures::Stream;
 use lightning::util::ser::Writeable;
 use log::error;
-use nostr::{Event, EventId, UnsignedEvent};
+use nostr::key::XOnlyPublicKey;
+use nostr::{Event, EventId, Timestamp, ToBech32, UnsignedEvent};
 use schnorr_fun::adaptor::{Adaptor, EncryptedSignature};
 use schnorr_fun::fun::marker::{EvenY, NonZero, Normal, Public};
 use schnorr_fun::fun::Point;
 use schnorr_fun::Message;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::str::FromStr;
 
-pub async fn health_check() -> Result<Json<bool>, (StatusCode, String)> {
+pub async fn health_check() -> Result<Json<bool>, ApiError> {
     Ok(Json(true))
 }
 
+#[derive(Serialize)]
+pub struct ServerInfo {
+    pubkey: XOnlyPublicKey,
+    npub: String,
+}
+
+/// The server's nostr identity, used for its DMs and on relays.
+pub async fn get_info(Extension(state): Extension<State>) -> Result<Json<ServerInfo>, ApiError> {
+    let pubkey = state.keys.public_key();
+    let npub = pubkey
+        .to_bech32()
+        .map_err(|e| ApiError::Internal(e.into()))?;
+    Ok(Json(ServerInfo { pubkey, npub }))
+}
+
 #[derive(Deserialize)]
 pub struct CreateBetRequest {
-    oracle_announcement: String,
+    /// Resolved from the oracle event id when not given
+    oracle_announcement: Option<String>,
     oracle_event_id: EventId,
     win_event: UnsignedEvent,
     lose_event: UnsignedEvent,
-    counterparty_win_event: UnsignedEvent,
-    counterparty_lose_event: UnsignedEvent,
-    sigs: HashMap<String, EncryptedSignature>,
+    /// The named counterparty's notes, left out for open bets
+    counterparty_win_event: Option<UnsignedEvent>,
+    counterparty_lose_event: Option<UnsignedEvent>,
+    /// Other named counterparties, for bets with more than two participants
+    #[serde(default)]
+    additional_counterparties: Vec<AdditionalCounterparty>,
+    /// What the notes of whoever accepts an open bet must look like
+    counterparty_win_template: Option<NoteTemplate>,
+    counterparty_lose_template: Option<NoteTemplate>,
+    /// Draw notes, published when a draw outcome happens. Either every
+    /// participant has one or nobody does
+    draw_event: Option<UnsignedEvent>,
+    counterparty_draw_event: Option<UnsignedEvent>,
+    counterparty_draw_template: Option<NoteTemplate>,
+    /// Outcomes nobody wins on, signed on the draw notes if there are any
+    #[serde(default)]
+    draw_outcomes: HashSet<String>,
+    /// Outcomes nothing is signed for, the bet ends with no contest
+    #[serde(default)]
+    no_contest_outcomes: HashSet<String>,
+    /// How many people an open bet takes including the proposer, defaults to two
+    participants: Option<usize>,
+    sigs: HashMap<String, OutcomeSigs>,
+    /// Values the proposer wins on, required for numeric events
+    win_range: Option<NumericRange>,
+    /// Other oracles attesting to the same event
+    #[serde(default)]
+    additional_oracles: Vec<AdditionalOracle>,
+    /// How many oracles must agree on the outcome, defaults to all of them
+    threshold: Option<usize>,
+    /// Unix timestamp after which the proposal expires
+    expires_at: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct AdditionalCounterparty {
+    win_event: UnsignedEvent,
+    lose_event: UnsignedEvent,
+    draw_event: Option<UnsignedEvent>,
+}
+
+#[derive(Deserialize)]
+pub struct AdditionalOracle {
+    oracle_announcement: Option<String>,
+    oracle_event_id: EventId,
+}
+
+/// The sigs for an outcome, one for each combination of `threshold` oracles
+/// in lexicographic order. Single oracle bets can pass just the sig.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum OutcomeSigs {
+    Single(EncryptedSignature),
+    Multiple(Vec<EncryptedSignature>),
+}
+
+impl OutcomeSigs {
+    fn len(&self) -> usize {
+        match self {
+            OutcomeSigs::Single(_) => 1,
+            OutcomeSigs::Multiple(sigs) => sigs.len(),
+        }
+    }
+
+    fn into_vec(self) -> Vec<EncryptedSignature> {
+        match self {
+            OutcomeSigs::Single(sig) => vec![sig],
+            OutcomeSigs::Multiple(sigs) => sigs,
+        }
+    }
+}
+
+/// Parses a JSON request body that was read as raw bytes for authentication.
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+    serde_json::from_slice(body)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid request body: {e}")))
 }
 
 pub fn verify_id(e: &UnsignedEvent) -> anyhow::Result<()> {
@@ -40,100 +149,564 @@ pub fn verify_id(e: &UnsignedEvent) -> anyhow::Result<()> {
     if id == e.id {
         Ok(())
     } else {
-        Err(anyhow!("Invalid event id {e:?}"))
+        Err(ApiError::InvalidSignature(format!("Invalid event id {}", e.id)).into())
+    }
+}
+
+fn verify_ids(notes: &OutcomeNotes) -> anyhow::Result<()> {
+    verify_id(&notes.win)?;
+    verify_id(&notes.lose)?;
+    if let Some(ref draw) = notes.draw {
+        verify_id(draw)?;
+    }
+    Ok(())
+}
+
+/// The outcomes a bet's sigs must cover, and for numeric events whether the
+/// proposer wins on each one.
+fn expected_outcomes(
+    announcement: &OracleAnnouncement,
+    win_range: Option<NumericRange>,
+) -> anyhow::Result<HashMap<String, Option<bool>>> {
+    match announcement.oracle_event.event_descriptor {
+        EventDescriptor::EnumEvent(ref desc) => {
+            Ok(desc.outcomes.iter().map(|o| (o.clone(), None)).collect())
+        }
+        EventDescriptor::DigitDecompositionEvent(ref desc) => {
+            let max = numeric::max_value(desc)?;
+            let win_range = win_range.ok_or(ApiError::BadRequest(
+                "Missing win range for numeric event".to_string(),
+            ))?;
+            if win_range.start > win_range.end || win_range.end > max {
+                anyhow::bail!(ApiError::BadRequest("Invalid win range".to_string()));
+            }
+
+            let lose_ranges = numeric::complement(win_range, max);
+            if lose_ranges.is_empty() {
+                anyhow::bail!(ApiError::BadRequest(
+                    "Win range covers every outcome".to_string()
+                ));
+            }
+
+            let base = desc.base as u64;
+            let nb_digits = desc.nb_digits as usize;
+            let mut outcomes = HashMap::new();
+            for prefix in numeric::decompose(win_range, base, nb_digits) {
+                outcomes.insert(numeric::prefix_to_outcome(&prefix), Some(true));
+            }
+            for range in lose_ranges {
+                for prefix in numeric::decompose(range, base, nb_digits) {
+                    outcomes.insert(numeric::prefix_to_outcome(&prefix), Some(false));
+                }
+            }
+
+            Ok(outcomes)
+        }
     }
 }
 
-async fn create_bet_impl(state: &State, request: CreateBetRequest) -> anyhow::Result<i32> {
-    let oracle_announcement = utils::oracle_announcement_from_str(&request.oracle_announcement)?;
-    let oracle_info = OracleInfo {
-        public_key: oracle_announcement.oracle_public_key,
-        nonces: oracle_announcement.oracle_event.oracle_nonces.clone(),
+/// The messages the oracle will sign if the given outcome happens.
+fn outcome_messages(
+    announcement: &OracleAnnouncement,
+    outcome: &str,
+) -> anyhow::Result<Vec<dlc::secp256k1_zkp::Message>> {
+    let digits = match announcement.oracle_event.event_descriptor {
+        EventDescriptor::EnumEvent(_) => vec![outcome.to_string()],
+        EventDescriptor::DigitDecompositionEvent(ref desc) => {
+            let prefix = numeric::outcome_to_prefix(outcome)?;
+            if prefix.is_empty()
+                || prefix.len() > desc.nb_digits as usize
+                || prefix.iter().any(|d| *d >= desc.base as u64)
+            {
+                anyhow::bail!(ApiError::InvalidSigs(format!(
+                    "Invalid numeric outcome: {outcome}"
+                )));
+            }
+            prefix.iter().map(|d| d.to_string()).collect()
+        }
     };
 
-    let all_outcomes = if let EventDescriptor::EnumEvent(ref desc) =
-        oracle_announcement.oracle_event.event_descriptor
+    Ok(digits
+        .iter()
+        .map(|d| dlc::secp256k1_zkp::Message::from_hashed_data::<sha256::Hash>(d.as_bytes()))
+        .collect())
+}
+
+/// Parses the announcements for every oracle of a new bet, checking they all
+/// describe the same event. Announcements that weren't given are resolved
+/// from their event id, these are marked as verified.
+async fn parse_oracles(
+    state: &State,
+    request: &CreateBetRequest,
+) -> anyhow::Result<(Vec<(OracleAnnouncement, EventId, bool)>, usize)> {
+    let count = request.additional_oracles.len() + 1;
+    if count > state.config.max_oracles {
+        anyhow::bail!(ApiError::BadRequest(format!(
+            "Bets can have at most {} oracles",
+            state.config.max_oracles
+        )));
+    }
+
+    let mut oracles = vec![];
+    let requested = std::iter::once((&request.oracle_announcement, request.oracle_event_id)).chain(
+        request
+            .additional_oracles
+            .iter()
+            .map(|o| (&o.oracle_announcement, o.oracle_event_id)),
+    );
+    for (encoded, event_id) in requested {
+        let oracle = match encoded {
+            Some(str) => {
+                let announcement = utils::oracle_announcement_from_str(str)
+                    .map_err(|_| ApiError::BadRequest("Invalid oracle announcement".to_string()))?;
+                announcement.validate(&state.secp).map_err(|_| {
+                    ApiError::InvalidSignature("Invalid oracle announcement signature".to_string())
+                })?;
+                (announcement, event_id, false)
+            }
+            None => {
+                let announcement = announcement::resolve_announcement(state, event_id)
+                    .await?
+                    .ok_or(ApiError::BadRequest(format!(
+                        "Announcement event {event_id} not found"
+                    )))?;
+                (announcement, event_id, true)
+            }
+        };
+        oracles.push(oracle);
+    }
+
+    let descriptor = &oracles[0].0.oracle_event.event_descriptor;
+    if oracles
+        .iter()
+        .any(|(a, _, _)| a.oracle_event.event_descriptor != *descriptor)
     {
-        desc.outcomes.clone()
-    } else {
-        anyhow::bail!("Only enum events supported");
+        anyhow::bail!(ApiError::BadRequest(
+            "Oracle announcements describe different events".to_string()
+        ));
+    }
+
+    let pubkeys = oracles
+        .iter()
+        .map(|(a, _, _)| a.oracle_public_key.serialize())
+        .collect::<HashSet<_>>();
+    let event_ids = oracles.iter().map(|(_, id, _)| *id).collect::<HashSet<_>>();
+    if pubkeys.len() != oracles.len() || event_ids.len() != oracles.len() {
+        anyhow::bail!(ApiError::BadRequest("Duplicate oracles".to_string()));
+    }
+
+    let threshold = request.threshold.unwrap_or(oracles.len());
+    if threshold == 0 || threshold > oracles.len() {
+        anyhow::bail!(ApiError::BadRequest(format!(
+            "Invalid threshold {threshold} for {} oracles",
+            oracles.len()
+        )));
+    }
+    // with a minority threshold, oracles could settle on different outcomes
+    if threshold * 2 <= oracles.len() {
+        anyhow::bail!(ApiError::BadRequest(format!(
+            "Threshold {threshold} must be a majority of the {} oracles",
+            oracles.len()
+        )));
+    }
+
+    Ok((oracles, threshold))
+}
+
+/// Checks the bet's oracles are allowed by the server's oracle policy.
+fn check_oracle_policy(state: &State, announcements: &[OracleAnnouncement]) -> anyhow::Result<()> {
+    if state.config.oracle_policy == OraclePolicy::Any {
+        return Ok(());
+    }
+
+    let mut conn = state.db_pool.get()?;
+    for announcement in announcements {
+        let pubkey = XOnlyPublicKey::from_slice(&announcement.oracle_public_key.serialize())?;
+        let status = Oracle::get_by_pubkey(&mut conn, pubkey)?.map(|o| o.trust_status());
+        let allowed = match state.config.oracle_policy {
+            OraclePolicy::Any => true,
+            OraclePolicy::Allowlist => status == Some(TrustStatus::Trusted),
+            OraclePolicy::Blocklist => status != Some(TrustStatus::Blocked),
+        };
+        if !allowed {
+            anyhow::bail!(ApiError::Forbidden(format!(
+                "Oracle {pubkey} is not accepted by this server"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies each adaptor sig against the participant's outcome notes,
+/// returning the sigs along with which note they are for.
+fn verify_sigs(
+    state: &State,
+    announcements: &[OracleAnnouncement],
+    threshold: usize,
+    notes: &OutcomeNotes,
+    sigs: HashMap<String, OutcomeSigs>,
+) -> anyhow::Result<HashMap<String, (Vec<EncryptedSignature>, SignedNote)>> {
+    let oracle_infos = announcements
+        .iter()
+        .map(|a| OracleInfo {
+            public_key: a.oracle_public_key,
+            nonces: a.oracle_event.oracle_nonces.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    // check the sig counts before building every combination
+    let nb_combinations = utils::binomial(oracle_infos.len(), threshold);
+    if let Some((outcome, sigs)) = sigs.iter().find(|(_, s)| s.len() != nb_combinations) {
+        anyhow::bail!(ApiError::InvalidSigs(format!(
+            "Incorrect number of sigs for outcome {outcome}, {} != {nb_combinations}",
+            sigs.len()
+        )));
+    }
+    let combinations = utils::combinations(oracle_infos.len(), threshold);
+
+    let verification_key: Point<EvenY, Public, NonZero> =
+        Point::from_xonly_bytes(notes.win.pubkey.serialize())
+            .ok_or(ApiError::BadRequest("invalid pubkey".to_string()))?;
+    let messages = SignedNote::ALL
+        .into_iter()
+        .filter_map(|n| {
+            let event = match n {
+                SignedNote::Win => Some(&notes.win),
+                SignedNote::Lose => Some(&notes.lose),
+                SignedNote::Draw => notes.draw.as_ref(),
+            };
+            event.map(|e| (n, Message::<Public>::raw(e.id.as_bytes())))
+        })
+        .collect::<Vec<_>>();
+    let mut verified: HashMap<String, (Vec<EncryptedSignature>, SignedNote)> =
+        HashMap::with_capacity(sigs.len());
+    for (outcome, sigs) in sigs {
+        let sigs = sigs.into_vec();
+
+        let msgs = outcome_messages(&announcements[0], &outcome)?;
+        let mut outcome_note = None;
+        for (combination, sig) in combinations.iter().zip(sigs.iter()) {
+            let infos = combination
+                .iter()
+                .map(|i| oracle_infos[*i].clone())
+                .collect::<Vec<_>>();
+            let point = dlc::get_adaptor_point_from_oracle_info(
+                &state.secp,
+                &infos,
+                &vec![msgs.clone(); threshold],
+            )
+            .map_err(|e| ApiError::BadRequest(format!("Invalid oracle info: {e}")))?;
+
+            let encryption_key: Point<Normal, Public, NonZero> =
+                Point::from_bytes(point.serialize()).ok_or(anyhow!("invalid pubkey"))?;
+
+            let note = messages
+                .iter()
+                .find(|(_, message)| {
+                    state.schnorr.verify_encrypted_signature(
+                        &verification_key,
+                        &encryption_key,
+                        *message,
+                        sig,
+                    )
+                })
+                .map(|(note, _)| *note)
+                .ok_or(ApiError::InvalidSignature("invalid sig".to_string()))?;
+
+            // every combination must sign the same event
+            if outcome_note.is_some_and(|n| n != note) {
+                anyhow::bail!(ApiError::InvalidSigs(format!(
+                    "Inconsistent sigs for outcome {outcome}"
+                )));
+            }
+            outcome_note = Some(note);
+        }
+
+        let note = outcome_note.ok_or(ApiError::InvalidSigs(format!(
+            "No sigs for outcome {outcome}"
+        )))?;
+        verified.insert(outcome, (sigs, note));
+    }
+
+    Ok(verified)
+}
+
+/// Checks a participant can both win and lose, otherwise there's no bet.
+fn check_sides(
+    sigs: &HashMap<String, (Vec<EncryptedSignature>, SignedNote)>,
+    who: &str,
+) -> anyhow::Result<()> {
+    let signs = |note: SignedNote| sigs.values().any(|(_, n)| *n == note);
+    if !signs(SignedNote::Win) || !signs(SignedNote::Lose) {
+        anyhow::bail!(ApiError::InvalidSigs(format!(
+            "{who} must win on some outcomes and lose on the others"
+        )));
+    }
+    Ok(())
+}
+
+/// Checks the draw outcomes are signed on the draw note, and nothing else is.
+fn check_draws(
+    sigs: &HashMap<String, (Vec<EncryptedSignature>, SignedNote)>,
+    draw_outcomes: &HashSet<String>,
+) -> anyhow::Result<()> {
+    for (outcome, (_, note)) in sigs.iter() {
+        let is_draw = draw_outcomes.contains(outcome);
+        if is_draw != (*note == SignedNote::Draw) {
+            anyhow::bail!(ApiError::InvalidSigs(format!(
+                "Sig for outcome {outcome} must {}be on the draw note",
+                if is_draw { "" } else { "not " }
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the outcomes declared as draws or as no contest are outcomes of the
+/// event, and that none of them is declared as both.
+fn check_declared_outcomes(
+    announcement: &OracleAnnouncement,
+    draw_outcomes: &HashSet<String>,
+    no_contest_outcomes: &HashSet<String>,
+) -> anyhow::Result<()> {
+    if draw_outcomes.is_empty() && no_contest_outcomes.is_empty() {
+        return Ok(());
+    }
+
+    let EventDescriptor::EnumEvent(ref desc) = announcement.oracle_event.event_descriptor else {
+        anyhow::bail!(ApiError::UnsupportedEvent(
+            "Only enum events can have draw or no contest outcomes".to_string()
+        ));
     };
+    if let Some(outcome) = draw_outcomes
+        .iter()
+        .chain(no_contest_outcomes.iter())
+        .find(|o| !desc.outcomes.contains(o))
+    {
+        anyhow::bail!(ApiError::BadRequest(format!("Unknown outcome: {outcome}")));
+    }
+    if let Some(outcome) = draw_outcomes.intersection(no_contest_outcomes).next() {
+        anyhow::bail!(ApiError::BadRequest(format!(
+            "Outcome {outcome} can't be both a draw and no contest"
+        )));
+    }
+
+    Ok(())
+}
 
-    if request.sigs.len() != all_outcomes.len() {
-        anyhow::bail!(
+/// Draw notes are optional, but either every participant has one or nobody
+/// does.
+fn check_draw_notes(has_draw: bool, counterparties: &Counterparties) -> anyhow::Result<()> {
+    let consistent = match counterparties {
+        Counterparties::Named(named) => named.iter().all(|n| n.draw.is_some() == has_draw),
+        Counterparties::Open { templates, .. } => templates.draw.is_some() == has_draw,
+    };
+    if !consistent {
+        anyhow::bail!(ApiError::BadRequest(
+            "Either every participant has a draw note or nobody does".to_string()
+        ));
+    }
+    Ok(())
+}
+
+async fn create_bet_impl(
+    state: &State,
+    pubkey: XOnlyPublicKey,
+    request: CreateBetRequest,
+) -> anyhow::Result<i32> {
+    if request.win_event.pubkey != pubkey {
+        anyhow::bail!(ApiError::Forbidden(
+            "only the proposer can create a bet".to_string()
+        ));
+    }
+
+    let (oracles, threshold) = parse_oracles(state, &request).await?;
+    let announcements = oracles
+        .iter()
+        .map(|(a, _, _)| a.clone())
+        .collect::<Vec<_>>();
+    check_oracle_policy(state, &announcements)?;
+    let mut expected = expected_outcomes(&announcements[0], request.win_range)?;
+    check_declared_outcomes(
+        &announcements[0],
+        &request.draw_outcomes,
+        &request.no_contest_outcomes,
+    )?;
+    if request.draw_event.is_some() && request.draw_outcomes.is_empty() {
+        anyhow::bail!(ApiError::BadRequest(
+            "Draw notes need draw outcomes".to_string()
+        ));
+    }
+    // draws are only signed when there are draw notes to sign
+    expected.retain(|outcome, _| {
+        !request.no_contest_outcomes.contains(outcome)
+            && (request.draw_event.is_some() || !request.draw_outcomes.contains(outcome))
+    });
+
+    let notes = OutcomeNotes {
+        win: request.win_event,
+        lose: request.lose_event,
+        draw: request.draw_event,
+    };
+    let counterparties = match (
+        request.counterparty_win_event,
+        request.counterparty_lose_event,
+        request.counterparty_win_template,
+        request.counterparty_lose_template,
+    ) {
+        (Some(win), Some(lose), None, None) if request.counterparty_draw_template.is_none() => {
+            let first = OutcomeNotes {
+                win,
+                lose,
+                draw: request.counterparty_draw_event,
+            };
+            let additional = request
+                .additional_counterparties
+                .into_iter()
+                .map(|c| OutcomeNotes {
+                    win: c.win_event,
+                    lose: c.lose_event,
+                    draw: c.draw_event,
+                });
+            Counterparties::Named(std::iter::once(first).chain(additional).collect())
+        }
+        (None, None, Some(win), Some(lose))
+            if request.additional_counterparties.is_empty()
+                && request.counterparty_draw_event.is_none() =>
+        {
+            Counterparties::Open {
+                templates: NoteTemplates {
+                    win,
+                    lose,
+                    draw: request.counterparty_draw_template,
+                },
+                participants: request.participants.unwrap_or(2),
+            }
+        }
+        _ => anyhow::bail!(ApiError::BadRequest(
+            "Bet needs either counterparty events or counterparty templates".to_string()
+        )),
+    };
+
+    let participant_count = counterparties.participant_count();
+    if participant_count < 2 || participant_count > state.config.max_participants {
+        anyhow::bail!(ApiError::BadRequest(format!(
+            "Bets must have between 2 and {} participants",
+            state.config.max_participants
+        )));
+    }
+    // numeric bets are split into a win range and the rest of the domain
+    if participant_count > 2
+        && !matches!(
+            announcements[0].oracle_event.event_descriptor,
+            EventDescriptor::EnumEvent(_)
+        )
+    {
+        anyhow::bail!(ApiError::UnsupportedEvent(
+            "Only enum events can have more than two participants".to_string()
+        ));
+    }
+
+    if request.sigs.len() != expected.len() {
+        anyhow::bail!(ApiError::InvalidSigs(format!(
             "Incorrect number of sigs, {} != {}",
             request.sigs.len(),
-            all_outcomes.len()
-        );
+            expected.len()
+        )));
+    }
+    if let Some(outcome) = request.sigs.keys().find(|o| !expected.contains_key(*o)) {
+        anyhow::bail!(ApiError::InvalidSigs(format!(
+            "Unexpected outcome: {outcome}"
+        )));
     }
 
     // verify ids
-    verify_id(&request.win_event)?;
-    verify_id(&request.lose_event)?;
-    verify_id(&request.counterparty_win_event)?;
-    verify_id(&request.counterparty_lose_event)?;
+    verify_ids(&notes)?;
+    validation::validate_notes(&state.config, &notes)?;
+    check_draw_notes(notes.draw.is_some(), &counterparties)?;
+    match &counterparties {
+        Counterparties::Named(named) => {
+            let mut pubkeys = HashSet::with_capacity(named.len());
+            for counterparty in named {
+                verify_ids(counterparty)?;
+                validation::validate_counterparty_notes(&state.config, pubkey, counterparty)?;
+                if !pubkeys.insert(counterparty.win.pubkey) {
+                    anyhow::bail!(ApiError::BadRequest(
+                        "Counterparties must be different".to_string()
+                    ));
+                }
+            }
+        }
+        Counterparties::Open { templates, .. } => {
+            validation::validate_templates(&state.config, templates)?
+        }
+    }
 
-    let verification_key: Point<EvenY, Public, NonZero> =
-        Point::from_xonly_bytes(request.win_event.pubkey.serialize())
-            .ok_or(anyhow::anyhow!("invalid pubkey"))?;
-    let win_message = Message::<Public>::raw(request.win_event.id.as_bytes());
-    let lose_message = Message::<Public>::raw(request.lose_event.id.as_bytes());
-    let mut sigs: HashMap<String, (EncryptedSignature, bool)> =
-        HashMap::with_capacity(request.sigs.len());
-    for (outcome, sig) in request.sigs {
-        let msg =
-            vec![dlc::secp256k1_zkp::Message::from_hashed_data::<sha256::Hash>(outcome.as_bytes())];
-        let point =
-            dlc::get_adaptor_point_from_oracle_info(&state.secp, &[oracle_info.clone()], &[msg])?;
-
-        let encryption_key: Point<Normal, Public, NonZero> =
-            Point::from_bytes(point.serialize()).ok_or(anyhow::anyhow!("invalid pubkey"))?;
-
-        let is_win = state.schnorr.verify_encrypted_signature(
-            &verification_key,
-            &encryption_key,
-            win_message,
-            &sig,
-        );
-
-        let is_lose = state.schnorr.verify_encrypted_signature(
-            &verification_key,
-            &encryption_key,
-            lose_message,
-            &sig,
-        );
-
-        if !is_win && !is_lose {
-            return Err(anyhow::anyhow!("invalid sig"));
-        }
-
-        sigs.insert(outcome, (sig, is_win));
+    let sigs = verify_sigs(state, &announcements, threshold, &notes, request.sigs)?;
+
+    // numeric bets must sign the win event for exactly the win range
+    for (outcome, (_, note)) in sigs.iter() {
+        if expected[outcome].is_some_and(|expected| expected != (*note == SignedNote::Win)) {
+            anyhow::bail!(ApiError::InvalidSigs(format!(
+                "Sig for outcome {outcome} does not match win range"
+            )));
+        }
+    }
+
+    check_draws(&sigs, &request.draw_outcomes)?;
+    check_sides(&sigs, "Proposer")?;
+
+    // make sure each oracle event id is for the announcement we were given
+    let mut verified_oracles = Vec::with_capacity(oracles.len());
+    for (announcement, event_id, resolved) in oracles {
+        let verified = resolved
+            || announcement::verify_announcement_event(state, &announcement, event_id).await?;
+        verified_oracles.push((announcement, event_id, verified));
     }
 
+    let now = chrono::Utc::now().naive_utc();
+    let expires_at = match request.expires_at {
+        Some(timestamp) => chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0)
+            .filter(|expires_at| *expires_at > now)
+            .ok_or(ApiError::BadRequest("Invalid expiry".to_string()))?,
+        None => now + chrono::Duration::seconds(state.config.proposal_expiry_secs as i64),
+    };
+
     let mut conn = state.db_pool.get()?;
+    let mut participants = vec![notes.win.pubkey];
+    if let Counterparties::Named(named) = &counterparties {
+        participants.extend(named.iter().map(|n| n.win.pubkey));
+    }
     let id = models::create_bet(
         &mut conn,
-        oracle_announcement,
-        request.win_event,
-        request.lose_event,
-        request.counterparty_win_event,
-        request.counterparty_lose_event,
-        request.oracle_event_id,
+        verified_oracles,
+        threshold as i32,
+        notes,
+        counterparties,
+        request.draw_outcomes.into_iter().collect(),
+        request.no_contest_outcomes.into_iter().collect(),
         sigs,
+        expires_at,
     )?;
+    updates::notify(state, participants, BetUpdate::Proposed { bet_id: id });
 
     Ok(id)
 }
 
 pub async fn create_bet(
     Extension(state): Extension<State>,
-    Json(request): Json<CreateBetRequest>,
-) -> Result<Json<i32>, (StatusCode, String)> {
-    match create_bet_impl(&state, request).await {
+    headers: HeaderMap,
+    uri: Uri,
+    body: Bytes,
+) -> Result<Json<i32>, ApiError> {
+    let pubkey = auth::require_auth(&state, &headers, &Method::POST, &uri, &body)?;
+    let request: CreateBetRequest = parse_body(&body)?;
+    match create_bet_impl(&state, pubkey, request).await {
         Ok(id) => Ok(Json(id)),
         Err(e) => {
             error!("Error creating bet: {e}");
-            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+            Err(e.into())
         }
     }
 }
@@ -141,96 +714,315 @@ pub async fn create_bet(
 #[derive(Deserialize)]
 pub struct AddSigsRequest {
     id: i32,
-    sigs: HashMap<String, EncryptedSignature>,
+    sigs: HashMap<String, OutcomeSigs>,
 }
 
-async fn add_sigs_impl(state: &State, request: AddSigsRequest) -> anyhow::Result<()> {
-    let mut conn = state.db_pool.get()?;
-    let bet = Bet::get_by_id(&mut conn, request.id)?.ok_or(anyhow::anyhow!("bet not found"))?;
+/// Checks the bet is still waiting on its counterparties.
+fn check_proposed(bet: &Bet) -> anyhow::Result<()> {
+    if bet.status() != BetStatus::Proposed {
+        anyhow::bail!(ApiError::Conflict(format!(
+            "bet is already {}",
+            bet.status().as_str()
+        )))
+    }
 
-    if !bet.needs_reply {
-        anyhow::bail!("bet already setup")
+    if bet.proposal_deadline() <= chrono::Utc::now().naive_utc() {
+        anyhow::bail!(ApiError::Conflict("bet proposal expired".to_string()))
     }
 
-    let all_outcomes = if let EventDescriptor::EnumEvent(ref desc) =
-        bet.oracle_announcement().oracle_event.event_descriptor
-    {
-        desc.outcomes.clone()
-    } else {
-        anyhow::bail!("Only enum events supported");
-    };
+    Ok(())
+}
 
-    if request.sigs.len() != all_outcomes.len() {
-        anyhow::bail!(
+/// Verifies a counterparty's sigs on their notes, returning them along with
+/// the bet's oracles. Whether someone else already wins on the same outcomes
+/// is checked when they're stored.
+fn verify_acceptance(
+    state: &State,
+    conn: &mut PgConnection,
+    bet: &Bet,
+    notes: &OutcomeNotes,
+    sigs: HashMap<String, OutcomeSigs>,
+) -> anyhow::Result<(
+    HashMap<String, (Vec<EncryptedSignature>, SignedNote)>,
+    Vec<BetOracle>,
+)> {
+    // counterparties must sign the same outcomes as the proposer, with the
+    // same ones on their draw note
+    let proposer_sigs = Sig::get_by_bet_id(conn, bet.id)?
+        .into_iter()
+        .filter(|s| s.participant_index == 0)
+        .collect::<Vec<_>>();
+    let draws = proposer_sigs
+        .iter()
+        .filter(|s| s.note() == SignedNote::Draw)
+        .map(|s| s.outcome.clone())
+        .collect::<HashSet<_>>();
+    let expected = proposer_sigs
+        .into_iter()
+        .map(|s| s.outcome)
+        .collect::<HashSet<_>>();
+
+    if sigs.len() != expected.len() {
+        anyhow::bail!(ApiError::InvalidSigs(format!(
             "Incorrect number of sigs, {} != {}",
-            request.sigs.len(),
-            all_outcomes.len()
-        );
+            sigs.len(),
+            expected.len()
+        )));
+    }
+    if let Some(outcome) = sigs.keys().find(|o| !expected.contains(*o)) {
+        anyhow::bail!(ApiError::InvalidSigs(format!(
+            "Unexpected outcome: {outcome}"
+        )));
     }
 
-    let oracle_announcement = bet.oracle_announcement();
-    let oracle_info = OracleInfo {
-        public_key: oracle_announcement.oracle_public_key,
-        nonces: oracle_announcement.oracle_event.oracle_nonces,
-    };
+    let oracles = BetOracle::get_by_bet_id(conn, bet.id)?;
+    let announcements = oracles
+        .iter()
+        .map(|o| o.oracle_announcement())
+        .collect::<Vec<_>>();
+    let sigs = verify_sigs(state, &announcements, bet.threshold as usize, notes, sigs)?;
+    check_draws(&sigs, &draws)?;
+    check_sides(&sigs, "Participant")?;
 
-    let verification_key: Point<EvenY, Public, NonZero> =
-        Point::from_xonly_bytes(bet.user_b().serialize())
-            .ok_or(anyhow::anyhow!("invalid pubkey"))?;
-    let win_b = bet.win_b();
-    let lose_b = bet.lose_b();
-    let win_message = Message::<Public>::raw(win_b.id.as_bytes());
-    let lose_message = Message::<Public>::raw(lose_b.id.as_bytes());
-    let mut sigs: HashMap<String, (EncryptedSignature, bool)> =
-        HashMap::with_capacity(request.sigs.len());
-    for (outcome, sig) in request.sigs {
-        let msg =
-            vec![dlc::secp256k1_zkp::Message::from_hashed_data::<sha256::Hash>(outcome.as_bytes())];
-        let point =
-            dlc::get_adaptor_point_from_oracle_info(&state.secp, &[oracle_info.clone()], &[msg])?;
-
-        let encryption_key: Point<Normal, Public, NonZero> =
-            Point::from_bytes(point.serialize()).ok_or(anyhow::anyhow!("invalid pubkey"))?;
-
-        let is_lose = state.schnorr.verify_encrypted_signature(
-            &verification_key,
-            &encryption_key,
-            lose_message,
-            &sig,
-        );
-
-        let is_win = state.schnorr.verify_encrypted_signature(
-            &verification_key,
-            &encryption_key,
-            win_message,
-            &sig,
-        );
-
-        if !is_win && !is_lose {
-            return Err(anyhow::anyhow!("invalid sig"));
-        }
-
-        sigs.insert(outcome, (sig, is_win));
-    }
-
-    let bet = models::add_sigs(&mut conn, request.id, sigs)?;
-
-    // notify new oracle event
+    Ok((sigs, oracles))
+}
+
+/// Starts listening for the attestations of a newly accepted bet.
+async fn watch_oracles(state: &State, oracles: &[BetOracle]) {
     let sender = state.event_channel.lock().await;
-    sender.send_if_modified(|current| current.insert(bet.oracle_event_id()));
+    sender.send_if_modified(|current| {
+        oracles.iter().fold(false, |modified, o| {
+            current.insert(o.oracle_event_id()) || modified
+        })
+    });
+}
+
+async fn add_sigs_impl(
+    state: &State,
+    pubkey: XOnlyPublicKey,
+    request: AddSigsRequest,
+) -> anyhow::Result<()> {
+    let mut conn = state.db_pool.get()?;
+    let bet = Bet::get_by_id(&mut conn, request.id)?
+        .ok_or(ApiError::NotFound("bet not found".to_string()))?;
+
+    let participant = BetParticipant::get_by_bet_id(&mut conn, bet.id)?
+        .into_iter()
+        .find(|p| p.pubkey() == pubkey && p.participant_index > 0)
+        .ok_or(ApiError::Forbidden(
+            "only counterparties can add sigs".to_string(),
+        ))?;
+    check_proposed(&bet)?;
+
+    let (sigs, oracles) =
+        verify_acceptance(state, &mut conn, &bet, &participant.notes(), request.sigs)?;
+
+    let bet = models::add_sigs(&mut conn, request.id, pubkey, sigs)?;
+    if bet.status() == BetStatus::Active {
+        updates::notify_bet(state, &mut conn, BetUpdate::Accepted { bet_id: bet.id });
+
+        // notify new oracle events
+        watch_oracles(state, &oracles).await;
+    }
 
     Ok(())
 }
 
 pub async fn add_sigs(
     Extension(state): Extension<State>,
-    Json(request): Json<AddSigsRequest>,
-) -> Result<Json<bool>, (StatusCode, String)> {
-    match add_sigs_impl(&state, request).await {
+    headers: HeaderMap,
+    uri: Uri,
+    body: Bytes,
+) -> Result<Json<bool>, ApiError> {
+    let pubkey = auth::require_auth(&state, &headers, &Method::POST, &uri, &body)?;
+    let request: AddSigsRequest = parse_body(&body)?;
+    match add_sigs_impl(&state, pubkey, request).await {
         Ok(_) => Ok(Json(true)),
         Err(e) => {
             error!("Error adding sigs: {e}");
-            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AcceptBetRequest {
+    id: i32,
+    win_event: UnsignedEvent,
+    lose_event: UnsignedEvent,
+    /// Required when the bet has a draw template
+    draw_event: Option<UnsignedEvent>,
+    sigs: HashMap<String, OutcomeSigs>,
+}
+
+/// Accepts an open bet with notes matching its templates. The first ones to
+/// get in become its counterparties.
+async fn accept_bet_impl(
+    state: &State,
+    pubkey: XOnlyPublicKey,
+    request: AcceptBetRequest,
+) -> anyhow::Result<()> {
+    let mut conn = state.db_pool.get()?;
+    let bet = Bet::get_by_id(&mut conn, request.id)?
+        .ok_or(ApiError::NotFound("bet not found".to_string()))?;
+
+    let templates = bet
+        .counterparty_templates()
+        .ok_or(ApiError::BadRequest("bet is not open".to_string()))?;
+    check_proposed(&bet)?;
+    let participants = BetParticipant::get_by_bet_id(&mut conn, bet.id)?;
+    if participants.len() >= bet.participant_count as usize {
+        anyhow::bail!(ApiError::Conflict("bet is already full".to_string()))
+    }
+
+    let notes = OutcomeNotes {
+        win: request.win_event,
+        lose: request.lose_event,
+        draw: request.draw_event,
+    };
+    if notes.win.pubkey != pubkey {
+        anyhow::bail!(ApiError::Forbidden(
+            "notes must be signed by the acceptor".to_string()
+        ))
+    }
+
+    verify_ids(&notes)?;
+    if !templates.win.matches(&notes.win) {
+        anyhow::bail!(ApiError::BadRequest(
+            "Win event does not match the bet's template".to_string()
+        ));
+    }
+    if !templates.lose.matches(&notes.lose) {
+        anyhow::bail!(ApiError::BadRequest(
+            "Lose event does not match the bet's template".to_string()
+        ));
+    }
+    let draw_matches = match (&templates.draw, &notes.draw) {
+        (Some(template), Some(draw)) => template.matches(draw),
+        (None, None) => true,
+        _ => false,
+    };
+    if !draw_matches {
+        anyhow::bail!(ApiError::BadRequest(
+            "Draw event does not match the bet's template".to_string()
+        ));
+    }
+    validation::validate_counterparty_notes(&state.config, participants[0].pubkey(), &notes)?;
+
+    let (sigs, oracles) = verify_acceptance(state, &mut conn, &bet, &notes, request.sigs)?;
+
+    let bet = models::accept_bet(&mut conn, bet.id, notes, sigs)?;
+    if bet.status() == BetStatus::Active {
+        updates::notify_bet(state, &mut conn, BetUpdate::Accepted { bet_id: bet.id });
+        watch_oracles(state, &oracles).await;
+    }
+
+    Ok(())
+}
+
+pub async fn accept_bet(
+    Extension(state): Extension<State>,
+    headers: HeaderMap,
+    uri: Uri,
+    body: Bytes,
+) -> Result<Json<bool>, ApiError> {
+    let pubkey = auth::require_auth(&state, &headers, &Method::POST, &uri, &body)?;
+    let request: AcceptBetRequest = parse_body(&body)?;
+    match accept_bet_impl(&state, pubkey, request).await {
+        Ok(_) => Ok(Json(true)),
+        Err(e) => {
+            error!("Error accepting bet: {e}");
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct OpenBet {
+    id: i32,
+    /// Who is already in, starting with the proposer
+    participants: Vec<XOnlyPublicKey>,
+    /// How many people the bet takes
+    participant_count: i32,
+    oracle_announcement: String,
+    oracle_event_id: EventId,
+    oracles: Vec<UserBetOracle>,
+    threshold: i32,
+    counterparty_win_template: NoteTemplate,
+    counterparty_lose_template: NoteTemplate,
+    counterparty_draw_template: Option<NoteTemplate>,
+    /// Outcomes nobody wins on yet, an acceptor can only win on these
+    available_outcomes: HashSet<String>,
+    draw_outcomes: Vec<String>,
+    no_contest_outcomes: Vec<String>,
+    /// Unix timestamp after which the bet can't be accepted
+    expires_at: i64,
+}
+
+fn list_open_bets_impl(state: &State) -> anyhow::Result<Vec<OpenBet>> {
+    let mut conn = state.db_pool.get()?;
+    let now = chrono::Utc::now().naive_utc();
+    let bets = Bet::get_open_bets(&mut conn)?
+        .into_iter()
+        .filter(|b| b.proposal_deadline() > now);
+
+    let mut open_bets = vec![];
+    for bet in bets {
+        let Some(templates) = bet.counterparty_templates() else {
+            continue;
+        };
+        let participants = BetParticipant::get_by_bet_id(&mut conn, bet.id)?
+            .iter()
+            .map(|p| p.pubkey())
+            .collect::<Vec<_>>();
+        if participants.len() >= bet.participant_count as usize {
+            continue;
+        }
+
+        let sigs = Sig::get_by_bet_id(&mut conn, bet.id)?;
+        let won = sigs
+            .iter()
+            .filter(|s| s.note() == SignedNote::Win)
+            .map(|s| s.outcome.clone())
+            .collect::<HashSet<_>>();
+        let available_outcomes = sigs
+            .into_iter()
+            .filter(|s| s.note() != SignedNote::Draw)
+            .map(|s| s.outcome)
+            .filter(|o| !won.contains(o))
+            .collect();
+
+        open_bets.push(OpenBet {
+            id: bet.id,
+            participants,
+            participant_count: bet.participant_count,
+            oracle_announcement: base64::encode(bet.oracle_announcement().encode()),
+            oracle_event_id: bet.oracle_event_id(),
+            oracles: get_user_bet_oracles(&mut conn, bet.id)?,
+            threshold: bet.threshold,
+            counterparty_win_template: templates.win,
+            counterparty_lose_template: templates.lose,
+            counterparty_draw_template: templates.draw,
+            available_outcomes,
+            draw_outcomes: bet.draw_outcomes.clone(),
+            no_contest_outcomes: bet.no_contest_outcomes.clone(),
+            expires_at: bet.proposal_deadline().timestamp(),
+        });
+    }
+
+    Ok(open_bets)
+}
+
+/// Lists the open bets anyone can still join. The participants' notes are
+/// left out so they can't be read before the bet settles.
+pub async fn list_open_bets(
+    Extension(state): Extension<State>,
+) -> Result<Json<Vec<OpenBet>>, ApiError> {
+    match list_open_bets_impl(&state) {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => {
+            error!("Error listing open bets: {e}");
+            Err(e.into())
         }
     }
 }
@@ -243,66 +1035,213 @@ pub struct ListEventsRequest {
 #[derive(Serialize)]
 pub struct UserBet {
     id: i32,
-    win_a: UnsignedEvent,
-    lose_a: UnsignedEvent,
-    win_b: UnsignedEvent,
-    lose_b: UnsignedEvent,
+    status: BetStatus,
+    /// Whether the outcome notes' contents were left out
+    redacted: bool,
+    /// Everyone taking part so far, starting with the proposer
+    participants: Vec<UserBetParticipant>,
+    /// How many people the bet takes
+    participant_count: i32,
     oracle_announcement: String,
     oracle_event_id: EventId,
     user_outcomes: HashSet<String>,
     counterparty_outcomes: HashSet<String>,
-    win_outcome_event_id: Option<EventId>,
-    lose_outcome_event_id: Option<EventId>,
+    /// Outcomes nobody wins on
+    draw_outcomes: Vec<String>,
+    /// Outcomes that end the bet with no contest
+    no_contest_outcomes: Vec<String>,
+    /// How the bet ended for the user, once it has
+    result: Option<BetResult>,
+    oracles: Vec<UserBetOracle>,
+    threshold: i32,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BetResult {
+    Won,
+    Lost,
+    Draw,
+    NoContest,
+}
+
+#[derive(Serialize)]
+pub struct UserBetParticipant {
+    pubkey: XOnlyPublicKey,
+    win_event: UnsignedEvent,
+    lose_event: UnsignedEvent,
+    draw_event: Option<UnsignedEvent>,
+    /// Whether they added their sigs
+    accepted: bool,
+    /// The outcome note that was published for them
+    outcome_event_id: Option<EventId>,
+}
+
+impl UserBet {
+    /// Hides the contents of the outcome notes so they can't be read before
+    /// the bet settles.
+    fn redact(&mut self) {
+        for participant in self.participants.iter_mut() {
+            participant.win_event.content = String::new();
+            participant.lose_event.content = String::new();
+            if let Some(ref mut draw) = participant.draw_event {
+                draw.content = String::new();
+            }
+        }
+        self.redacted = true;
+    }
+}
+
+/// Checks who is listing a user's bets, returning whether they may see the
+/// full outcome notes.
+fn authorize_listing(
+    state: &State,
+    headers: &HeaderMap,
+    uri: &Uri,
+    request: &ListEventsRequest,
+) -> Result<bool, ApiError> {
+    match auth::authenticate(&state.config, headers, &Method::GET, uri, None)? {
+        Some(pubkey) if pubkey.to_string() == request.pubkey.to_lowercase() => Ok(true),
+        Some(_) => Err(ApiError::Forbidden(
+            "auth pubkey does not match requested pubkey".to_string(),
+        )),
+        None => match state.config.unauthenticated_listing {
+            UnauthenticatedListing::Redacted => Ok(false),
+            UnauthenticatedListing::Denied => Err(ApiError::Unauthorized(
+                "authentication required".to_string(),
+            )),
+        },
+    }
+}
+
+/// Streams updates for a user's bets as server-sent events. Only the user
+/// can subscribe, the updates reveal their bets and published outcome notes.
+pub async fn stream_updates(
+    Extension(state): Extension<State>,
+    headers: HeaderMap,
+    uri: Uri,
+    Query(request): Query<ListEventsRequest>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, Infallible>>>, ApiError> {
+    if !authorize_listing(&state, &headers, &uri, &request)? {
+        return Err(ApiError::Unauthorized(
+            "authentication required".to_string(),
+        ));
+    }
+    let pubkey = XOnlyPublicKey::from_str(&request.pubkey)
+        .map_err(|_| ApiError::BadRequest("invalid pubkey".to_string()))?;
+
+    let stream = updates::stream(state.updates.subscribe(), pubkey);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Serialize)]
+pub struct UserBetOracle {
+    oracle_announcement: String,
+    oracle_event_id: EventId,
+    /// Whether the oracle event id was checked against a nostr announcement event
+    announcement_verified: bool,
+}
+
+fn get_user_bet_oracles(
+    conn: &mut PgConnection,
+    bet_id: i32,
+) -> anyhow::Result<Vec<UserBetOracle>> {
+    let oracles = BetOracle::get_by_bet_id(conn, bet_id)?
+        .into_iter()
+        .map(|o| UserBetOracle {
+            oracle_announcement: base64::encode(o.oracle_announcement().encode()),
+            oracle_event_id: o.oracle_event_id(),
+            announcement_verified: o.announcement_verified,
+        })
+        .collect();
+    Ok(oracles)
+}
+
+/// Builds the listing of a bet as seen by one of its participants.
+fn get_user_bet(
+    conn: &mut PgConnection,
+    bet: Bet,
+    pubkey: XOnlyPublicKey,
+) -> anyhow::Result<UserBet> {
+    let participants = BetParticipant::get_by_bet_id(conn, bet.id)?;
+    let user = participants.iter().find(|p| p.pubkey() == pubkey);
+    let user_index = user.map(|p| p.participant_index);
+    let accepted = user.is_some_and(|p| p.accepted_at.is_some());
+
+    let sigs = Sig::get_by_bet_id(conn, bet.id)?;
+    let counterparty_outcomes = sigs
+        .iter()
+        .filter(|s| s.note() == SignedNote::Win && Some(s.participant_index) != user_index)
+        .map(|s| s.outcome.clone())
+        .collect::<HashSet<_>>();
+    // until they've added their sigs, users can win on whatever is left
+    let user_outcomes = sigs
+        .iter()
+        .filter(|s| match accepted {
+            true => s.note() == SignedNote::Win && Some(s.participant_index) == user_index,
+            false => s.note() != SignedNote::Draw && !counterparty_outcomes.contains(&s.outcome),
+        })
+        .map(|s| s.outcome.clone())
+        .collect();
+
+    let result = match bet.status() {
+        BetStatus::Settled => user
+            .and_then(|p| p.published_note())
+            .map(|note| match note {
+                SignedNote::Win => BetResult::Won,
+                SignedNote::Lose => BetResult::Lost,
+                SignedNote::Draw => BetResult::Draw,
+            }),
+        BetStatus::Drawn => Some(BetResult::Draw),
+        BetStatus::NoContest => Some(BetResult::NoContest),
+        _ => None,
+    };
+
+    Ok(UserBet {
+        id: bet.id,
+        status: bet.status(),
+        redacted: false,
+        participants: participants
+            .into_iter()
+            .map(|p| UserBetParticipant {
+                pubkey: p.pubkey(),
+                win_event: p.win_event(),
+                lose_event: p.lose_event(),
+                draw_event: p.draw_event(),
+                accepted: p.accepted_at.is_some(),
+                outcome_event_id: p.outcome_event_id(),
+            })
+            .collect(),
+        participant_count: bet.participant_count,
+        oracle_announcement: base64::encode(bet.oracle_announcement().encode()),
+        oracle_event_id: bet.oracle_event_id(),
+        user_outcomes,
+        counterparty_outcomes,
+        draw_outcomes: bet.draw_outcomes.clone(),
+        no_contest_outcomes: bet.no_contest_outcomes.clone(),
+        result,
+        oracles: get_user_bet_oracles(conn, bet.id)?,
+        threshold: bet.threshold,
+    })
 }
 
 pub async fn list_pending_events_impl(
     state: &State,
     request: ListEventsRequest,
 ) -> anyhow::Result<Vec<UserBet>> {
-    let pubkey = nostr::key::XOnlyPublicKey::from_str(&request.pubkey)?;
+    let pubkey = nostr::key::XOnlyPublicKey::from_str(&request.pubkey)
+        .map_err(|_| ApiError::BadRequest("invalid pubkey".to_string()))?;
     let mut conn = state.db_pool.get()?;
-    let bets = Bet::get_pending_bets(&mut conn, pubkey)?;
+    // expired proposals can't be joined anymore, even before they're marked so
+    let now = chrono::Utc::now().naive_utc();
+    let bets = Bet::get_pending_bets(&mut conn, pubkey, now)?
+        .into_iter()
+        .filter(|b| b.proposal_deadline() > now)
+        .collect::<Vec<_>>();
 
     let mut pending_bets = Vec::with_capacity(bets.len());
     for bet in bets {
-        let oracle_announcement = bet.oracle_announcement();
-        let win_a = bet.win_a();
-        let lose_a = bet.lose_a();
-        let win_b = bet.win_b();
-        let lose_b = bet.lose_b();
-        let sigs = Sig::get_by_bet_id(&mut conn, bet.id)?;
-        let is_a = win_a.pubkey == pubkey;
-        let outcomes_a = sigs
-            .into_iter()
-            .filter(|s| s.is_party_a && s.is_win)
-            .map(|s| s.outcome)
-            .collect::<HashSet<_>>();
-
-        let mut outcomes_b = match oracle_announcement.oracle_event.event_descriptor {
-            EventDescriptor::EnumEvent(ref events) => HashSet::from_iter(events.outcomes.clone()),
-            EventDescriptor::DigitDecompositionEvent(_) => continue,
-        };
-        outcomes_b.retain(|o| !outcomes_a.contains(o));
-
-        let (user_outcomes, counterparty_outcomes) = if is_a {
-            (outcomes_a, outcomes_b)
-        } else {
-            (outcomes_b, outcomes_a)
-        };
-
-        pending_bets.push(UserBet {
-            id: bet.id,
-            win_a,
-            lose_a,
-            win_b,
-            lose_b,
-            oracle_announcement: base64::encode(oracle_announcement.encode()),
-            oracle_event_id: bet.oracle_event_id(),
-            user_outcomes,
-            counterparty_outcomes,
-            win_outcome_event_id: None,
-            lose_outcome_event_id: None,
-        });
+        pending_bets.push(get_user_bet(&mut conn, bet, pubkey)?);
     }
 
     Ok(pending_bets)
@@ -310,13 +1249,21 @@ pub async fn list_pending_events_impl(
 
 pub async fn list_pending_events(
     Extension(state): Extension<State>,
+    headers: HeaderMap,
+    uri: Uri,
     Query(request): Query<ListEventsRequest>,
-) -> Result<Json<Vec<UserBet>>, (StatusCode, String)> {
+) -> Result<Json<Vec<UserBet>>, ApiError> {
+    let authorized = authorize_listing(&state, &headers, &uri, &request)?;
     match list_pending_events_impl(&state, request).await {
-        Ok(res) => Ok(Json(res)),
+        Ok(mut res) => {
+            if !authorized {
+                res.iter_mut().for_each(UserBet::redact);
+            }
+            Ok(Json(res))
+        }
         Err(e) => {
             error!("Error listing pending events: {e}");
-            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+            Err(e.into())
         }
     }
 }
@@ -325,122 +1272,372 @@ pub async fn list_events_impl(
     state: &State,
     request: ListEventsRequest,
 ) -> anyhow::Result<Vec<UserBet>> {
-    let pubkey = nostr::key::XOnlyPublicKey::from_str(&request.pubkey)?;
+    let pubkey = nostr::key::XOnlyPublicKey::from_str(&request.pubkey)
+        .map_err(|_| ApiError::BadRequest("invalid pubkey".to_string()))?;
     let mut conn = state.db_pool.get()?;
     let bets = Bet::get_active_bets(&mut conn, pubkey)?;
 
-    let mut pending_bets = Vec::with_capacity(bets.len());
+    let mut user_bets = Vec::with_capacity(bets.len());
     for bet in bets {
-        let oracle_announcement = bet.oracle_announcement();
-        let win_a = bet.win_a();
-        let lose_a = bet.lose_a();
-        let win_b = bet.win_b();
-        let lose_b = bet.lose_b();
-        let sigs = Sig::get_by_bet_id(&mut conn, bet.id)?;
-        let user_a_outcomes = sigs
-            .iter()
-            .filter(|s| s.is_party_a)
-            .map(|s| s.outcome.clone())
-            .collect::<HashSet<_>>();
-        let user_b_outcomes = sigs
-            .into_iter()
-            .filter(|s| !s.is_party_a)
-            .map(|s| s.outcome)
-            .collect::<HashSet<_>>();
-
-        let (user, counterparty) = if bet.user_a() == pubkey {
-            (user_a_outcomes, user_b_outcomes)
-        } else {
-            (user_b_outcomes, user_a_outcomes)
-        };
-
-        pending_bets.push(UserBet {
-            id: bet.id,
-            win_a,
-            lose_a,
-            win_b,
-            lose_b,
-            oracle_announcement: base64::encode(oracle_announcement.encode()),
-            oracle_event_id: bet.oracle_event_id(),
-            user_outcomes: user,
-            counterparty_outcomes: counterparty,
-            win_outcome_event_id: bet.win_outcome_event_id(),
-            lose_outcome_event_id: bet.lose_outcome_event_id(),
-        });
+        user_bets.push(get_user_bet(&mut conn, bet, pubkey)?);
     }
 
-    Ok(pending_bets)
+    Ok(user_bets)
 }
 
 pub async fn list_events(
     Extension(state): Extension<State>,
+    headers: HeaderMap,
+    uri: Uri,
     Query(request): Query<ListEventsRequest>,
-) -> Result<Json<Vec<UserBet>>, (StatusCode, String)> {
+) -> Result<Json<Vec<UserBet>>, ApiError> {
+    let authorized = authorize_listing(&state, &headers, &uri, &request)?;
     match list_events_impl(&state, request).await {
-        Ok(res) => Ok(Json(res)),
+        Ok(mut res) => {
+            if !authorized {
+                res.iter_mut().for_each(UserBet::redact);
+            }
+            Ok(Json(res))
+        }
         Err(e) => {
             error!("Error listing events: {e}");
-            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+            Err(e.into())
         }
     }
 }
 
-pub async fn get_counts(
-    Extension(state): Extension<State>,
-) -> Result<Json<Counts>, (StatusCode, String)> {
+pub async fn get_counts(Extension(state): Extension<State>) -> Result<Json<Counts>, ApiError> {
     let mut conn = state
         .db_pool
         .get()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| ApiError::Internal(e.into()))?;
     match models::get_counts(&mut conn) {
         Ok(res) => Ok(Json(res)),
         Err(e) => {
             error!("Error listing counts: {e}");
-            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+            Err(e.into())
         }
     }
 }
 
 pub async fn get_event_ids(
     Extension(state): Extension<State>,
-) -> Result<Json<Vec<EventId>>, (StatusCode, String)> {
+) -> Result<Json<Vec<EventId>>, ApiError> {
     let mut conn = state
         .db_pool
         .get()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    match Bet::get_event_ids(&mut conn) {
+        .map_err(|e| ApiError::Internal(e.into()))?;
+    match BetParticipant::get_outcome_event_ids(&mut conn) {
         Ok(res) => Ok(Json(res)),
         Err(e) => {
             error!("Error listing event_ids: {e}");
-            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+            Err(e.into())
+        }
+    }
+}
+
+/// How many outcome notes each broadcast target took or failed, admin only as
+/// it names the internal endpoints.
+pub async fn get_broadcast_stats(
+    Extension(state): Extension<State>,
+    headers: HeaderMap,
+    uri: Uri,
+) -> Result<Json<Vec<BroadcastStats>>, ApiError> {
+    auth::require_admin(&state, &headers, &Method::GET, &uri, &[])?;
+    Ok(Json(state.broadcasters.stats()))
+}
+
+#[derive(Serialize)]
+pub struct OracleEntry {
+    pubkey: XOnlyPublicKey,
+    name: String,
+    description: Option<String>,
+    relays: Vec<String>,
+    trust_status: TrustStatus,
+}
+
+impl From<Oracle> for OracleEntry {
+    fn from(oracle: Oracle) -> Self {
+        Self {
+            pubkey: oracle.pubkey(),
+            trust_status: oracle.trust_status(),
+            name: oracle.name,
+            description: oracle.description,
+            relays: oracle.relays,
+        }
+    }
+}
+
+/// Lists the oracles in the registry, so clients can show their names.
+pub async fn get_oracles(
+    Extension(state): Extension<State>,
+) -> Result<Json<Vec<OracleEntry>>, ApiError> {
+    let mut conn = state
+        .db_pool
+        .get()
+        .map_err(|e| ApiError::Internal(e.into()))?;
+    match Oracle::get_all(&mut conn) {
+        Ok(res) => Ok(Json(res.into_iter().map(OracleEntry::from).collect())),
+        Err(e) => {
+            error!("Error listing oracles: {e}");
+            Err(e.into())
         }
     }
 }
 
 #[derive(Deserialize)]
-pub struct RejectBetRequest {
-    pub id: i32,
-    pub sig: Event,
+pub struct SetOracleRequest {
+    pubkey: XOnlyPublicKey,
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    relays: Vec<String>,
+    trust_status: TrustStatus,
 }
 
-pub async fn reject(
+/// Adds an oracle to the registry or updates it.
+pub async fn set_oracle(
     Extension(state): Extension<State>,
-    Json(request): Json<RejectBetRequest>,
-) -> Result<Json<bool>, (StatusCode, String)> {
+    headers: HeaderMap,
+    uri: Uri,
+    body: Bytes,
+) -> Result<Json<OracleEntry>, ApiError> {
+    auth::require_admin(&state, &headers, &Method::POST, &uri, &body)?;
+    let request: SetOracleRequest = parse_body(&body)?;
+    let mut conn = state
+        .db_pool
+        .get()
+        .map_err(|e| ApiError::Internal(e.into()))?;
+    match Oracle::upsert(
+        &mut conn,
+        request.pubkey,
+        request.name,
+        request.description,
+        request.relays,
+        request.trust_status,
+    ) {
+        Ok(oracle) => Ok(Json(oracle.into())),
+        Err(e) => {
+            error!("Error setting oracle: {e}");
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RemoveOracleRequest {
+    pubkey: XOnlyPublicKey,
+}
+
+/// Removes an oracle from the registry.
+pub async fn remove_oracle(
+    Extension(state): Extension<State>,
+    headers: HeaderMap,
+    uri: Uri,
+    body: Bytes,
+) -> Result<Json<bool>, ApiError> {
+    auth::require_admin(&state, &headers, &Method::POST, &uri, &body)?;
+    let request: RemoveOracleRequest = parse_body(&body)?;
     let mut conn = state
         .db_pool
         .get()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| ApiError::Internal(e.into()))?;
+    match Oracle::delete(&mut conn, request.pubkey) {
+        Ok(true) => Ok(Json(true)),
+        Ok(false) => Err(ApiError::NotFound("oracle not found".to_string())),
+        Err(e) => {
+            error!("Error removing oracle: {e}");
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SettingsRequest {
+    dm_opt_out: Option<bool>,
+    private: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct SettingsResponse {
+    dm_opt_out: bool,
+    private: bool,
+}
+
+/// Updates the caller's settings, like opting out of DM notifications or
+/// being left out of published bets.
+pub async fn set_settings(
+    Extension(state): Extension<State>,
+    headers: HeaderMap,
+    uri: Uri,
+    body: Bytes,
+) -> Result<Json<SettingsResponse>, ApiError> {
+    let pubkey = auth::require_auth(&state, &headers, &Method::POST, &uri, &body)?;
+    let request: SettingsRequest = parse_body(&body)?;
+    let mut conn = state
+        .db_pool
+        .get()
+        .map_err(|e| ApiError::Internal(e.into()))?;
+    match UserSettings::update(&mut conn, pubkey, request.dm_opt_out, request.private) {
+        Ok(settings) => Ok(Json(SettingsResponse {
+            dm_opt_out: settings.dm_opt_out,
+            private: settings.private,
+        })),
+        Err(e) => {
+            error!("Error setting settings: {e}");
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BetHistoryRequest {
+    id: i32,
+}
+
+#[derive(Serialize)]
+pub struct BetHistoryEntry {
+    from_status: Option<BetStatus>,
+    to_status: BetStatus,
+    /// Who made the change, if it wasn't the server
+    actor: Option<XOnlyPublicKey>,
+    /// The signed event the change was made with
+    event: Option<Event>,
+    created_at: i64,
+}
+
+async fn get_bet_history_impl(
+    state: &State,
+    pubkey: XOnlyPublicKey,
+    request: BetHistoryRequest,
+) -> anyhow::Result<Vec<BetHistoryEntry>> {
+    let mut conn = state.db_pool.get()?;
+    let bet = Bet::get_by_id(&mut conn, request.id)?
+        .ok_or(ApiError::NotFound("bet not found".to_string()))?;
+
+    let participants = BetParticipant::get_by_bet_id(&mut conn, bet.id)?;
+    if !participants.iter().any(|p| p.pubkey() == pubkey) {
+        anyhow::bail!(ApiError::Forbidden(
+            "only participants can see a bet's history".to_string()
+        ));
+    }
+
+    let history = BetHistory::get_by_bet_id(&mut conn, bet.id)?
+        .into_iter()
+        .map(|h| BetHistoryEntry {
+            from_status: h.from_status(),
+            to_status: h.to_status(),
+            actor: h.actor(),
+            event: h.event(),
+            created_at: h.created_at().timestamp(),
+        })
+        .collect();
+
+    Ok(history)
+}
+
+/// Lists every status change of a bet, for its participants.
+pub async fn get_bet_history(
+    Extension(state): Extension<State>,
+    headers: HeaderMap,
+    uri: Uri,
+    Query(request): Query<BetHistoryRequest>,
+) -> Result<Json<Vec<BetHistoryEntry>>, ApiError> {
+    let pubkey = auth::authenticate(&state.config, &headers, &Method::GET, &uri, None)?.ok_or(
+        ApiError::Unauthorized("authentication required".to_string()),
+    )?;
+    match get_bet_history_impl(&state, pubkey, request).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => {
+            error!("Error getting bet history: {e}");
+            Err(e.into())
+        }
+    }
+}
 
-    if request.sig.verify().is_err() || request.sig.content != format!("reject {}", request.id) {
-        return Err((StatusCode::BAD_REQUEST, "invalid sig".to_string()));
+#[derive(Deserialize)]
+pub struct RejectBetRequest {
+    pub id: i32,
+    pub sig: Event,
+}
+
+/// Verifies a signed action on a bet. The event's content must be
+/// `{action} {id} {oracle_event_id} {created_at}` so it can't be reused for
+/// another bet, and it must be recent and not used before.
+fn verify_bet_action(
+    state: &State,
+    conn: &mut PgConnection,
+    action: &str,
+    bet: &Bet,
+    event: &Event,
+) -> anyhow::Result<()> {
+    let expected = format!(
+        "{action} {} {} {}",
+        bet.id,
+        bet.oracle_event_id().to_hex(),
+        bet.created_at().timestamp()
+    );
+    if event.verify().is_err() || event.content != expected {
+        anyhow::bail!(ApiError::InvalidSignature("invalid sig".to_string()));
+    }
+
+    let now = Timestamp::now().as_u64();
+    if now.abs_diff(event.created_at.as_u64()) > state.config.auth_window_secs {
+        anyhow::bail!(ApiError::InvalidSignature("sig expired".to_string()));
     }
 
-    match models::reject_bet(&mut conn, request.id, request.sig.pubkey) {
+    UsedEvent::mark_used(conn, event.id, event.pubkey)?;
+
+    Ok(())
+}
+
+async fn reject_impl(state: &State, request: RejectBetRequest) -> anyhow::Result<()> {
+    let mut conn = state.db_pool.get()?;
+    let bet = Bet::get_by_id(&mut conn, request.id)?
+        .ok_or(ApiError::NotFound("bet not found".to_string()))?;
+
+    verify_bet_action(state, &mut conn, "reject", &bet, &request.sig)?;
+    models::reject_bet(&mut conn, bet.id, request.sig.pubkey, &request.sig)?;
+    updates::notify_bet(state, &mut conn, BetUpdate::Rejected { bet_id: bet.id });
+
+    Ok(())
+}
+
+pub async fn reject(
+    Extension(state): Extension<State>,
+    Json(request): Json<RejectBetRequest>,
+) -> Result<Json<bool>, ApiError> {
+    match reject_impl(&state, request).await {
         Ok(_) => Ok(Json(true)),
         Err(e) => {
             error!("Error rejecting event: {e}");
-            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+            Err(e.into())
+        }
+    }
+}
+
+async fn cancel_impl(state: &State, request: RejectBetRequest) -> anyhow::Result<()> {
+    let mut conn = state.db_pool.get()?;
+    let bet = Bet::get_by_id(&mut conn, request.id)?
+        .ok_or(ApiError::NotFound("bet not found".to_string()))?;
+
+    verify_bet_action(state, &mut conn, "cancel", &bet, &request.sig)?;
+    models::cancel_bet(&mut conn, bet.id, request.sig.pubkey, &request.sig)?;
+    updates::notify_bet(state, &mut conn, BetUpdate::Cancelled { bet_id: bet.id });
+
+    Ok(())
+}
+
+/// Lets the proposer withdraw a bet before the counterparty answers.
+pub async fn cancel(
+    Extension(state): Extension<State>,
+    Json(request): Json<RejectBetRequest>,
+) -> Result<Json<bool>, ApiError> {
+    match cancel_impl(&state, request).await {
+        Ok(_) => Ok(Json(true)),
+        Err(e) => {
+            error!("Error cancelling bet: {e}");
+            Err(e.into())
         }
     }
 }
@@ -0,0 +1,151 @@
+use crate::models::bet_participant::BetParticipant;
+use crate::State;
+use axum::response::sse;
+use diesel::PgConnection;
+use futures::Stream;
+use log::{debug, error};
+use nostr::key::XOnlyPublicKey;
+use nostr::EventId;
+use serde::Serialize;
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+
+/// How many updates can be queued for a slow subscriber before it misses some
+pub const UPDATES_CAPACITY: usize = 1024;
+
+/// Something that happened to a bet, pushed to its participants.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BetUpdate {
+    Proposed {
+        bet_id: i32,
+    },
+    Accepted {
+        bet_id: i32,
+    },
+    Rejected {
+        bet_id: i32,
+    },
+    Cancelled {
+        bet_id: i32,
+    },
+    Expired {
+        bet_id: i32,
+    },
+    Voided {
+        bet_id: i32,
+    },
+    Attested {
+        bet_id: i32,
+        oracle_event_id: EventId,
+    },
+    Settled {
+        bet_id: i32,
+        win_outcome_event_id: Option<EventId>,
+        lose_outcome_event_ids: Vec<EventId>,
+    },
+    Drawn {
+        bet_id: i32,
+        draw_outcome_event_ids: Vec<EventId>,
+    },
+    NoContest {
+        bet_id: i32,
+    },
+}
+
+impl BetUpdate {
+    pub fn bet_id(&self) -> i32 {
+        match *self {
+            BetUpdate::Proposed { bet_id }
+            | BetUpdate::Accepted { bet_id }
+            | BetUpdate::Rejected { bet_id }
+            | BetUpdate::Cancelled { bet_id }
+            | BetUpdate::Expired { bet_id }
+            | BetUpdate::Voided { bet_id }
+            | BetUpdate::Attested { bet_id, .. }
+            | BetUpdate::Settled { bet_id, .. }
+            | BetUpdate::Drawn { bet_id, .. }
+            | BetUpdate::NoContest { bet_id } => bet_id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BetUpdate::Proposed { .. } => "proposed",
+            BetUpdate::Accepted { .. } => "accepted",
+            BetUpdate::Rejected { .. } => "rejected",
+            BetUpdate::Cancelled { .. } => "cancelled",
+            BetUpdate::Expired { .. } => "expired",
+            BetUpdate::Voided { .. } => "voided",
+            BetUpdate::Attested { .. } => "attested",
+            BetUpdate::Settled { .. } => "settled",
+            BetUpdate::Drawn { .. } => "drawn",
+            BetUpdate::NoContest { .. } => "no_contest",
+        }
+    }
+}
+
+/// An update along with who it should be sent to.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub participants: Vec<XOnlyPublicKey>,
+    pub update: BetUpdate,
+}
+
+/// Sends the update to everyone subscribed for one of the participants.
+pub fn notify(state: &State, participants: Vec<XOnlyPublicKey>, update: BetUpdate) {
+    // an error only means nobody is subscribed
+    if state
+        .updates
+        .send(Notification {
+            participants,
+            update,
+        })
+        .is_err()
+    {
+        debug!("No subscribers for bet update");
+    }
+}
+
+/// Sends the update to everyone taking part in the bet.
+pub fn notify_bet(state: &State, conn: &mut PgConnection, update: BetUpdate) {
+    let bet_id = update.bet_id();
+    match BetParticipant::get_by_bet_id(conn, bet_id) {
+        Ok(participants) => notify(
+            state,
+            participants.iter().map(|p| p.pubkey()).collect(),
+            update,
+        ),
+        Err(e) => error!("Error getting participants of bet {bet_id}: {e}"),
+    }
+}
+
+/// Turns the updates into SSE events for the given pubkey.
+pub fn stream(
+    receiver: Receiver<Notification>,
+    pubkey: XOnlyPublicKey,
+) -> impl Stream<Item = Result<sse::Event, Infallible>> {
+    futures::stream::unfold(receiver, move |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) => {
+                    if !notification.participants.contains(&pubkey) {
+                        continue;
+                    }
+                    let event = sse::Event::default()
+                        .event(notification.update.name())
+                        .json_data(&notification.update)
+                        .unwrap_or_default();
+                    return Some((Ok(event), receiver));
+                }
+                // let the client know to refetch what it missed
+                Err(RecvError::Lagged(_)) => {
+                    let event = sse::Event::default().event("lagged").data("");
+                    return Some((Ok(event), receiver));
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
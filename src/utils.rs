@@ -1,5 +1,7 @@
 use anyhow::Result;
-use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+use dlc::secp256k1_zkp::hashes::sha256;
+use dlc::secp256k1_zkp::{All, Message, Secp256k1};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation};
 use lightning::util::ser::Readable;
 use nostr::hashes::hex::FromHex;
 use std::io::Cursor;
@@ -27,3 +29,141 @@ pub(crate) fn oracle_attestation_from_str(str: &str) -> Result<OracleAttestation
 
     OracleAttestation::read(&mut cursor).map_err(|_| anyhow::anyhow!("invalid oracle attestation"))
 }
+
+/// All `k` sized subsets of `0..n` in lexicographic order.
+pub(crate) fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 || k > n {
+        return vec![];
+    }
+
+    let mut res = vec![];
+    let mut current: Vec<usize> = (0..k).collect();
+    loop {
+        res.push(current.clone());
+
+        // find the rightmost index that can still be incremented
+        let Some(i) = (0..k).rev().find(|&i| current[i] < n - k + i) else {
+            return res;
+        };
+        current[i] += 1;
+        for j in i + 1..k {
+            current[j] = current[j - 1] + 1;
+        }
+    }
+}
+
+/// How many subsets [`combinations`] returns, without building them.
+/// Saturates instead of overflowing.
+pub(crate) fn binomial(n: usize, k: usize) -> usize {
+    if k == 0 || k > n {
+        return 0;
+    }
+
+    let k = k.min(n - k);
+    (0..k).fold(1usize, |acc, i| acc.saturating_mul(n - i) / (i + 1))
+}
+
+/// Checks the attestation was signed by the announcement's oracle, using the
+/// announced nonces, over outcomes the event can have.
+pub(crate) fn verify_attestation(
+    secp: &Secp256k1<All>,
+    announcement: &OracleAnnouncement,
+    attestation: &OracleAttestation,
+) -> Result<()> {
+    if attestation.oracle_public_key != announcement.oracle_public_key {
+        anyhow::bail!("attestation is from a different oracle");
+    }
+
+    let nonces = &announcement.oracle_event.oracle_nonces;
+    let expected = match announcement.oracle_event.event_descriptor {
+        EventDescriptor::EnumEvent(ref desc) => {
+            if !attestation
+                .outcomes
+                .first()
+                .is_some_and(|o| desc.outcomes.contains(o))
+            {
+                anyhow::bail!("attested outcome is not part of the event");
+            }
+            1
+        }
+        EventDescriptor::DigitDecompositionEvent(ref desc) => {
+            let base = desc.base as u64;
+            if attestation
+                .outcomes
+                .iter()
+                .any(|d| d.parse::<u64>().map_or(true, |d| d >= base))
+            {
+                anyhow::bail!("attested digit is not valid for the event");
+            }
+            desc.nb_digits as usize
+        }
+    };
+    if attestation.outcomes.len() != expected
+        || attestation.signatures.len() != expected
+        || nonces.len() < expected
+    {
+        anyhow::bail!("attestation has the wrong number of outcomes");
+    }
+
+    for ((sig, outcome), nonce) in attestation
+        .signatures
+        .iter()
+        .zip(attestation.outcomes.iter())
+        .zip(nonces.iter())
+    {
+        let (r, _) = dlc::secp_utils::schnorrsig_decompose(sig)?;
+        if r != *nonce {
+            anyhow::bail!("attestation does not use the announced nonces");
+        }
+
+        let msg = Message::from_hashed_data::<sha256::Hash>(outcome.as_bytes());
+        secp.verify_schnorr(sig, &msg, &announcement.oracle_public_key)
+            .map_err(|_| anyhow::anyhow!("invalid attestation signature"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combinations_in_lexicographic_order() {
+        assert_eq!(
+            combinations(4, 2),
+            vec![
+                vec![0, 1],
+                vec![0, 2],
+                vec![0, 3],
+                vec![1, 2],
+                vec![1, 3],
+                vec![2, 3],
+            ]
+        );
+        assert_eq!(combinations(3, 3), vec![vec![0, 1, 2]]);
+        assert_eq!(combinations(3, 1), vec![vec![0], vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn combinations_out_of_range() {
+        assert!(combinations(3, 0).is_empty());
+        assert!(combinations(3, 4).is_empty());
+    }
+
+    #[test]
+    fn binomial_matches_combinations() {
+        for n in 0..10 {
+            for k in 0..=n + 1 {
+                assert_eq!(binomial(n, k), combinations(n, k).len(), "{n} choose {k}");
+            }
+        }
+    }
+
+    #[test]
+    fn binomial_of_large_sets() {
+        assert_eq!(binomial(20, 10), 184_756);
+        // too many to count, but never less than what can be checked
+        assert!(binomial(1_000, 500) > u32::MAX as usize);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::models::bet::{NoteTemplates, OutcomeNotes};
+use nostr::key::XOnlyPublicKey;
+use nostr::{Kind, Timestamp, UnsignedEvent};
+
+/// Checks the proposer's outcome notes against the configured policy before
+/// anything is stored.
+pub fn validate_notes(config: &Config, notes: &OutcomeNotes) -> anyhow::Result<()> {
+    validate_side(config, "", notes)
+}
+
+/// Checks the counterparty's outcome notes, whether they come with the
+/// proposal or when accepting an open bet.
+pub fn validate_counterparty_notes(
+    config: &Config,
+    proposer: XOnlyPublicKey,
+    notes: &OutcomeNotes,
+) -> anyhow::Result<()> {
+    validate_side(config, "counterparty ", notes)?;
+    if notes.win.pubkey == proposer {
+        anyhow::bail!(ApiError::BadRequest(
+            "Proposer and counterparty must be different".to_string()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks the templates of an open bet describe notes the policy allows.
+pub fn validate_templates(config: &Config, templates: &NoteTemplates) -> anyhow::Result<()> {
+    let draw = templates.draw.as_ref().map(|d| ("draw", d));
+    for (name, template) in [("win", &templates.win), ("lose", &templates.lose)]
+        .into_iter()
+        .chain(draw)
+    {
+        validate_kind(config, template.kind)
+            .and_then(|_| validate_content(config, &template.content))
+            .map_err(|reason| {
+                ApiError::BadRequest(format!("Invalid counterparty {name} template: {reason}"))
+            })?;
+    }
+
+    Ok(())
+}
+
+fn validate_side(config: &Config, prefix: &str, notes: &OutcomeNotes) -> anyhow::Result<()> {
+    let same_pubkey = notes.lose.pubkey == notes.win.pubkey
+        && notes.draw.iter().all(|d| d.pubkey == notes.win.pubkey);
+    if !same_pubkey {
+        let side = if prefix.is_empty() {
+            "Win"
+        } else {
+            "Counterparty win"
+        };
+        anyhow::bail!(ApiError::BadRequest(format!(
+            "{side}, lose and draw events must have the same pubkey"
+        )));
+    }
+
+    let draw = notes.draw.as_ref().map(|d| ("draw", d));
+    for (name, event) in [("win", &notes.win), ("lose", &notes.lose)]
+        .into_iter()
+        .chain(draw)
+    {
+        validate_note(config, event).map_err(|reason| {
+            ApiError::BadRequest(format!("Invalid {prefix}{name} event: {reason}"))
+        })?;
+    }
+
+    Ok(())
+}
+
+fn validate_note(config: &Config, event: &UnsignedEvent) -> Result<(), String> {
+    validate_kind(config, event.kind)?;
+
+    let now = Timestamp::now().as_u64();
+    let created_at = event.created_at.as_u64();
+    if created_at > now + config.max_note_future_secs {
+        return Err("created_at is in the future".to_string());
+    }
+    if now.saturating_sub(created_at) > config.max_note_age_secs {
+        return Err("created_at is too old".to_string());
+    }
+
+    validate_content(config, &event.content)
+}
+
+fn validate_kind(config: &Config, kind: Kind) -> Result<(), String> {
+    let kind = kind.as_u64();
+    if !config.note_kind.contains(&kind) {
+        return Err(format!("kind {kind} is not allowed"));
+    }
+    Ok(())
+}
+
+fn validate_content(config: &Config, content: &str) -> Result<(), String> {
+    if content.len() > config.max_note_content_len {
+        return Err(format!(
+            "content is longer than {} bytes",
+            config.max_note_content_len
+        ));
+    }
+    Ok(())
+}